@@ -0,0 +1,154 @@
+use pest::iterators::Pair;
+
+use crate::error::ParseError;
+use crate::{invalid_color, parse_color_space, parse_date, parse_number, parse_operator, parse_raw, try_parse_date_like, ColorSpace, Date, Operator, Rule};
+
+// Zero-copy counterparts of the AST types: identifiers, strings, number and boolean literals (color components too) and comments
+// borrow the input text. Strings and quoted keys are kept as written, escapes included.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtomRef<'a> { String(&'a str), Ident(&'a str), Number { value: f64, text: &'a str }, Date(Date), Bool { value: bool, text: &'a str } }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyRef<'a> { Ident(&'a str), Number { value: f64, text: &'a str }, Date(Date), Quoted(&'a str) }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    Atom(AtomRef<'a>),
+    Array(Vec<AtomRef<'a>>),
+    // Each component's value and source text
+    Color { space: ColorSpace, components: Vec<(f64, &'a str)> },
+    Block(Vec<ItemRef<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemRef<'a> {
+    Pair { key: KeyRef<'a>, op: Operator, value: ValueRef<'a>, trailing_comment: Option<&'a str> },
+    Value { value: ValueRef<'a>, trailing_comment: Option<&'a str> },
+    Comment(&'a str),
+}
+
+// Parse into the borrowed AST. Accepts and rejects exactly what parse_str does.
+pub fn parse_str_borrowed(input: &str) -> Result<Vec<ItemRef<'_>>, ParseError> {
+    let mut pairs = parse_raw(input)?;
+    let mut items = Vec::new();
+    for child in pairs.next().unwrap().into_inner() {
+        if child.as_rule() == Rule::body { items = borrow_body(child)?; }
+    }
+    Ok(items)
+}
+
+// Body items, with a comment on the line where the previous item ends attached to it
+fn borrow_body(body: Pair<'_, Rule>) -> Result<Vec<ItemRef<'_>>, ParseError> {
+    let input = body.as_span().get_input();
+    let children = body.into_inner();
+    let mut items: Vec<ItemRef> = Vec::with_capacity(children.len());
+    let mut prev_end = None;
+    for it in children {
+        let span = it.as_span();
+        let item = borrow_item(it)?;
+        if let (ItemRef::Comment(text), Some(end)) = (&item, prev_end) {
+            if !input[end..span.start()].contains('\n') {
+                if let Some(ItemRef::Pair { trailing_comment: slot @ None, .. } | ItemRef::Value { trailing_comment: slot @ None, .. }) = items.last_mut() {
+                    *slot = Some(text);
+                    prev_end = None;
+                    continue;
+                }
+            }
+        }
+        prev_end = Some(span.end());
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn borrow_item(p: Pair<'_, Rule>) -> Result<ItemRef<'_>, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::item => return borrow_item(p.into_inner().next().unwrap()),
+        Rule::pair => {
+            let mut it = p.into_inner();
+            let key = borrow_key(it.next().unwrap())?;
+            let op = parse_operator(it.next().unwrap());
+            let value = borrow_value(it.next().unwrap())?;
+            ItemRef::Pair { key, op, value, trailing_comment: None }
+        }
+        Rule::bare_pair => {
+            let key = p.into_inner().next().unwrap();
+            let (line, col) = key.line_col();
+            return Err(ParseError::MissingOperator { key: key.as_str().to_string(), line, col });
+        }
+        Rule::comment => ItemRef::Comment(p.as_str()),
+        _ => ItemRef::Value { value: borrow_value(p)?, trailing_comment: None },
+    })
+}
+
+fn borrow_key(p: Pair<'_, Rule>) -> Result<KeyRef<'_>, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::key => borrow_key(p.into_inner().next().unwrap())?,
+        Rule::number => KeyRef::Number { value: parse_number(&p)?, text: p.as_str() },
+        Rule::date => KeyRef::Date(parse_date(&p)?),
+        Rule::quoted_date => KeyRef::Date(Date { quoted: true, ..parse_date(&p.into_inner().next().unwrap())? }),
+        Rule::string => KeyRef::Quoted(p.into_inner().next().unwrap().as_str()),
+        _ => KeyRef::Ident(p.as_str()),
+    })
+}
+
+fn borrow_value(p: Pair<'_, Rule>) -> Result<ValueRef<'_>, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::value => borrow_value(p.into_inner().next().unwrap())?,
+        Rule::block => {
+            let mut items = Vec::new();
+            for child in p.into_inner() {
+                if child.as_rule() == Rule::body { items = borrow_body(child)?; }
+            }
+            // Same classification as Value::from_items: only plain atoms make an array
+            if items.iter().all(|it| matches!(it, ItemRef::Value { value: ValueRef::Atom(_), trailing_comment: None })) {
+                ValueRef::Array(items.into_iter().filter_map(|it| match it { ItemRef::Value { value: ValueRef::Atom(a), .. } => Some(a), _ => None }).collect())
+            } else {
+                ValueRef::Block(items)
+            }
+        }
+        Rule::color => {
+            let mut inner = p.into_inner();
+            let space = parse_color_space(inner.next().unwrap().as_str());
+            let components = inner.map(|n| Ok((parse_number(&n)?, n.as_str()))).collect::<Result<_, ParseError>>()?;
+            ValueRef::Color { space, components }
+        }
+        Rule::bad_color => return Err(invalid_color(&p)),
+        _ => ValueRef::Atom(borrow_atom(p)?),
+    })
+}
+
+fn borrow_atom(p: Pair<'_, Rule>) -> Result<AtomRef<'_>, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::string => {
+            let s = p.into_inner().next().unwrap().as_str();
+            match try_parse_date_like(s) { Some(d) => AtomRef::Date(Date { quoted: true, ..d }), None => AtomRef::String(s) }
+        }
+        Rule::number => AtomRef::Number { value: parse_number(&p)?, text: p.as_str() },
+        Rule::date => AtomRef::Date(parse_date(&p)?),
+        Rule::boolean => AtomRef::Bool { value: p.as_str().eq_ignore_ascii_case("yes"), text: p.as_str() },
+        _ => AtomRef::Ident(p.as_str()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether `s` is a slice of `input` rather than a copy
+    fn points_into(input: &str, s: &str) -> bool {
+        input.as_bytes().as_ptr_range().contains(&s.as_ptr())
+    }
+
+    #[test]
+    fn text_borrows_the_input() {
+        let input = String::from("focus = { id = GER_rhineland text = \"Remilitarize\" } # opening\n");
+        let items = parse_str_borrowed(&input).unwrap();
+        let ItemRef::Pair { key: KeyRef::Ident(key), value: ValueRef::Block(inner), trailing_comment: Some(comment), .. } = &items[0] else { panic!("{:?}", items) };
+        assert!(points_into(&input, key) && points_into(&input, comment));
+        let ItemRef::Pair { value: ValueRef::Atom(AtomRef::Ident(id)), .. } = &inner[0] else { panic!() };
+        let ItemRef::Pair { value: ValueRef::Atom(AtomRef::String(text)), .. } = &inner[1] else { panic!() };
+        assert_eq!((*id, *text), ("GER_rhineland", "Remilitarize"));
+        assert!(points_into(&input, id) && points_into(&input, text));
+    }
+}
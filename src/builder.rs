@@ -0,0 +1,105 @@
+use crate::{bare_atom, bare_key, Atom, Boolean, Item, KeyAtom, Number, Operator, Value};
+
+// Fluent construction of an item list:
+// `BlockBuilder::new().pair("cost", 10.0).block("modifier", |b| b.pair("stability_factor", 0.1)).build()`
+#[derive(Debug, Clone, Default)]
+pub struct BlockBuilder { items: Vec<Item> }
+
+impl BlockBuilder {
+    pub fn new() -> Self { Self::default() }
+
+    // `key = value`
+    pub fn pair(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.items.push(Item::Pair { key: key_atom(key), op: Operator::Eq, value: value.into(), trailing_comment: None, leading_comments: Vec::new() });
+        self
+    }
+
+    // `key = { v1 v2 ... }`
+    pub fn array<V: Into<Atom>>(self, key: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.pair(key, Value::Array(values.into_iter().map(Into::into).collect()))
+    }
+
+    // `key = { ... }`, filled in by `f` and classified by Value::from_items, as when parsed
+    pub fn block(self, key: &str, f: impl FnOnce(BlockBuilder) -> BlockBuilder) -> Self {
+        self.pair(key, Value::from_items(f(BlockBuilder::new()).build()))
+    }
+
+    pub fn build(self) -> Vec<Item> { self.items }
+}
+
+// Key as the parser would read the text: bare identifiers, numbers and dates keep their type, anything else is quoted
+fn key_atom(key: &str) -> KeyAtom {
+    bare_key(key).unwrap_or_else(|| KeyAtom::Quoted(key.to_string()))
+}
+
+// Text read the way the parser reads a bare token: `yes`/`no`, numbers and dates become Bool, Number
+// and Date atoms, other bare words identifiers. Text that cannot be written bare is a quoted string.
+impl From<&str> for Atom {
+    fn from(s: &str) -> Self {
+        bare_atom(s).unwrap_or_else(|| Atom::String(s.to_string()))
+    }
+}
+
+impl From<f64> for Atom {
+    fn from(n: f64) -> Self { Atom::Number(Number::from(n)) }
+}
+
+impl From<bool> for Atom {
+    fn from(b: bool) -> Self { Atom::Bool(Boolean::from(b)) }
+}
+
+impl From<Atom> for Value {
+    fn from(a: Atom) -> Self { Value::Atom(a) }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self { Value::Atom(s.into()) }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self { Value::Atom(n.into()) }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self { Value::Atom(b.into()) }
+}
+
+// Collect items into a container, classified like parsed text (see Value::from_items)
+impl FromIterator<Item> for Value {
+    fn from_iter<I: IntoIterator<Item = Item>>(iter: I) -> Self { Value::from_items(iter.into_iter().collect()) }
+}
+
+impl FromIterator<Atom> for Value {
+    fn from_iter<I: IntoIterator<Item = Atom>>(iter: I) -> Self { Value::Array(iter.into_iter().collect()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, serialize_ast};
+
+    #[test]
+    fn builds_and_serializes_an_idea() {
+        let items = BlockBuilder::new()
+            .block("GER_rearmament", |b| {
+                b.pair("picture", "generic_industry")
+                    .block("allowed", |b| b.pair("always", false))
+                    .block("modifier", |b| b.pair("industrial_capacity_factory", 0.1).pair("stability_factor", -0.05))
+                    .array("provinces", [64.0, 65.0])
+                    .pair("cost", 150.0)
+            })
+            .build();
+        let expected = "GER_rearmament = {\n  picture = generic_industry\n  allowed = {\n    always = no\n  }\n  modifier = {\n    industrial_capacity_factory = 0.1\n    stability_factor = -0.05\n  }\n  provinces = {\n    64 65\n  }\n  cost = 150\n}\n";
+        assert_eq!(serialize_ast(&items), expected);
+        assert_eq!(parse_str(expected).unwrap(), items);
+    }
+
+    #[test]
+    fn text_values_are_classified_like_parsed_text() {
+        let built = BlockBuilder::new().pair("a", "yes").pair("b", "10").pair("c", "1936.1.1").pair("d", "GER").pair("e", "two words").pair("1936.1.1", "NO").build();
+        assert_eq!(built, parse_str("a = yes\nb = 10\nc = 1936.1.1\nd = GER\ne = \"two words\"\n1936.1.1 = NO").unwrap());
+        assert!(matches!(Atom::from("-1.5e3"), Atom::Number(n) if n.value == -1500.0));
+        assert_eq!(Atom::from("1936.13.1"), Atom::String("1936.13.1".into()));
+        assert_eq!(Atom::from("rgb"), Atom::Ident("rgb".into()));
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+
+use crate::{Date, DAYS_IN_MONTH};
+
+impl Date {
+    // Calendar date, or None when the day does not exist (`1936.2.30`).
+    // chrono uses the Gregorian calendar, so leap days are valid there even though the game has none.
+    pub fn to_naive_date(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(i32::try_from(self.y).ok()?, self.m.into(), self.d.into())
+    }
+
+    // Date and time of day, absent parts counting as 0
+    pub fn to_naive_datetime(&self) -> Option<NaiveDateTime> {
+        self.to_naive_date()?.and_hms_opt(self.h.unwrap_or(0).into(), self.min.unwrap_or(0).into(), self.sec.unwrap_or(0).into())
+    }
+}
+
+// Unquoted date without an hour. Fails for days the game's calendar lacks (February 29, which it has
+// no leap years for) and for years before 0; the error names the component, as parse_date_str does.
+impl TryFrom<NaiveDate> for Date {
+    type Error = &'static str;
+
+    fn try_from(date: NaiveDate) -> Result<Self, Self::Error> {
+        let y = u32::try_from(date.year()).map_err(|_| "year out of range")?;
+        let (m, d) = (date.month() as u8, date.day() as u8);
+        if d > DAYS_IN_MONTH[m as usize - 1] { return Err("day out of range"); }
+        Ok(Date { y, m, d, h: None, min: None, sec: None, quoted: false, literal: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, Atom, Item, Value};
+
+    fn date(s: &str) -> Date {
+        match &parse_str(&format!("a = {}", s)).unwrap()[0] {
+            Item::Pair { value: Value::Atom(Atom::Date(d)), .. } => d.clone(),
+            other => panic!("not a date: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn converts_to_and_from_chrono() {
+        let naive = NaiveDate::from_ymd_opt(1936, 3, 7).unwrap();
+        assert_eq!(date("1936.3.7").to_naive_date(), Some(naive));
+        assert_eq!(Date::try_from(naive), Ok(date("1936.3.7")));
+        assert_eq!(date("1936.3.7.12").to_naive_datetime(), naive.and_hms_opt(12, 0, 0));
+    }
+
+    #[test]
+    fn rejects_days_one_calendar_lacks() {
+        let d = Date { y: 1936, m: 2, d: 30, h: None, min: None, sec: None, quoted: false, literal: None };
+        assert_eq!(d.to_naive_date(), None);
+        assert_eq!(Date::try_from(NaiveDate::from_ymd_opt(1936, 2, 29).unwrap()), Err("day out of range"));
+        assert_eq!(Date::try_from(NaiveDate::from_ymd_opt(-1, 1, 1).unwrap()), Err("year out of range"));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{ColorSpace, Item, Number, Value};
+
+// Convert every color value to `target`, recursing into nested blocks
+pub fn normalize_colors(items: &mut [Item], target: ColorSpace) {
+    for it in items.iter_mut() {
+        match it {
+            Item::Pair { value, .. } | Item::Value { value, .. } => normalize_value(value, target),
+            Item::Comment(_) | Item::Blank(_) => {}
+        }
+    }
+}
+
+fn normalize_value(v: &mut Value, target: ColorSpace) {
+    match v {
+        Value::Color { space, components } if *space != target => {
+            let values: Vec<f64> = components.iter().map(|n| n.value).collect();
+            if let Some(converted) = convert_color(*space, &values, target) {
+                *space = target;
+                *components = converted.into_iter().map(Number::from).collect();
+            }
+        }
+        Value::Block(items) => normalize_colors(items, target),
+        _ => {}
+    }
+}
+
+// Convert color components between spaces. Ranges: rgb 0-255 (rounded to integers),
+// hsv 0-1 (rounded to 3 decimals), hsv360 hue 0-360 with saturation/value 0-100 (rounded to integers).
+// An optional 4th (alpha) component is rescaled like the value channel.
+// Returns None when there are fewer than 3 components.
+pub fn convert_color(space: ColorSpace, components: &[f64], target: ColorSpace) -> Option<Vec<f64>> {
+    if components.len() < 3 { return None; }
+    let (a, b, c) = (components[0], components[1], components[2]);
+    // Normalize to rgb in 0-1, plus alpha in 0-1
+    let (scale, rgb) = match space {
+        ColorSpace::Rgb => (255.0, (a / 255.0, b / 255.0, c / 255.0)),
+        ColorSpace::Hsv => (1.0, hsv_to_rgb(a, b, c)),
+        ColorSpace::Hsv360 => (100.0, hsv_to_rgb(a / 360.0, b / 100.0, c / 100.0)),
+    };
+    let alpha = components.get(3).map(|x| x / scale);
+    let (mut out, alpha_scale) = match target {
+        ColorSpace::Rgb => (vec![round(rgb.0 * 255.0, 0), round(rgb.1 * 255.0, 0), round(rgb.2 * 255.0, 0)], 255.0),
+        ColorSpace::Hsv => {
+            let (h, s, v) = rgb_to_hsv(rgb);
+            (vec![round(h, 3), round(s, 3), round(v, 3)], 1.0)
+        }
+        ColorSpace::Hsv360 => {
+            let (h, s, v) = rgb_to_hsv(rgb);
+            (vec![round(h * 360.0, 0), round(s * 100.0, 0), round(v * 100.0, 0)], 100.0)
+        }
+    };
+    if let Some(alpha) = alpha {
+        out.push(round(alpha * alpha_scale, if target == ColorSpace::Hsv { 3 } else { 0 }));
+    }
+    Some(out)
+}
+
+// Hue, saturation and value in 0-1 to rgb in 0-1
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let h = (h.rem_euclid(1.0)) * 6.0;
+    let s = s.clamp(0.0, 1.0);
+    let v = v.clamp(0.0, 1.0);
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (r + m, g + m, b + m)
+}
+
+// Rgb in 0-1 to hue, saturation and value in 0-1
+fn rgb_to_hsv((r, g, b): (f64, f64, f64)) -> (f64, f64, f64) {
+    let (r, g, b) = (r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    (h, s, max)
+}
+
+fn round(x: f64, decimals: i32) -> f64 {
+    let f = 10f64.powi(decimals);
+    (x * f).round() / f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+    use ColorSpace::*;
+
+    #[test]
+    fn converts_known_colors() {
+        let cases: &[(ColorSpace, &[f64], ColorSpace, &[f64])] = &[
+            (Rgb, &[255.0, 0.0, 0.0], Hsv, &[0.0, 1.0, 1.0]),
+            (Hsv, &[0.0, 1.0, 1.0], Rgb, &[255.0, 0.0, 0.0]),
+            (Rgb, &[0.0, 255.0, 0.0], Hsv360, &[120.0, 100.0, 100.0]),
+            (Hsv360, &[240.0, 100.0, 100.0], Rgb, &[0.0, 0.0, 255.0]),
+            (Rgb, &[128.0, 128.0, 128.0], Hsv, &[0.0, 0.0, 0.502]),
+            (Hsv, &[0.5, 0.0, 0.5], Rgb, &[128.0, 128.0, 128.0]),
+            (Hsv, &[0.0, 0.0, 0.0], Hsv360, &[0.0, 0.0, 0.0]),
+            (Rgb, &[255.0, 0.0, 0.0, 51.0], Hsv, &[0.0, 1.0, 1.0, 0.2]),
+            (Hsv, &[0.0, 1.0, 1.0, 0.5], Rgb, &[255.0, 0.0, 0.0, 128.0]),
+        ];
+        for (from, input, to, expected) in cases {
+            assert_eq!(convert_color(*from, input, *to).as_deref(), Some(*expected), "{:?} {:?} -> {:?}", from, input, to);
+        }
+        assert_eq!(convert_color(Rgb, &[1.0, 2.0], Hsv), None);
+    }
+
+    #[test]
+    fn normalize_converts_nested_colors_only() {
+        let mut items = parse_str("a = rgb { 255 0 0 }\nb = { c = hsv { 0 0 1 } }\nd = hsv { 0 1 1 }\n").unwrap();
+        normalize_colors(&mut items, Hsv);
+        assert_eq!(items, parse_str("a = hsv { 0 1 1 }\nb = { c = hsv { 0 0 1 } }\nd = hsv { 0 1 1 }\n").unwrap());
+    }
+}
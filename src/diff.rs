@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use crate::path::NodePath;
+use crate::{key_text, Item, Value};
+
+// One difference between two item lists. Pairs are located by their dotted key path;
+// bare values and comments, which have no key, by their index in the enclosing list.
+// Removed paths index the old list, Added and Modified paths the new one.
+// Modified holds the whole old and new pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added { path: NodePath },
+    Removed { path: NodePath },
+    Modified { path: NodePath, old: Box<Item>, new: Box<Item> },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    // Skip comment lines and trailing comments, so reformatted comments are not reported
+    pub ignore_comments: bool,
+}
+
+// Keyed differences between `a` and `b`, with default options
+pub fn diff(a: &[Item], b: &[Item]) -> Vec<Change> {
+    diff_with(a, b, &DiffOptions::default())
+}
+
+// Keyed differences between `a` and `b`. The n-th pair with a key in `a` is compared with the
+// n-th pair with that key in `b`; when both values are blocks the comparison recurses into them,
+// otherwise a differing pair is reported as Modified. Bare values and comments are matched by equality;
+// blank lines are never reported.
+pub fn diff_with(a: &[Item], b: &[Item], opts: &DiffOptions) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_list(a, b, &NodePath::default(), opts, &mut changes);
+    changes
+}
+
+fn diff_list(a: &[Item], b: &[Item], parent: &NodePath, opts: &DiffOptions, changes: &mut Vec<Change>) {
+    let new_pairs = keyed(b);
+    let mut matched = vec![false; b.len()];
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (i, old) in a.iter().enumerate() {
+        let Item::Pair { key, .. } = old else { continue };
+        let name = key_text(key);
+        let nth = seen.entry(name.clone()).or_insert(0);
+        let counterpart = new_pairs.get(&name).and_then(|idx| idx.get(*nth)).copied();
+        *nth += 1;
+        let Some(j) = counterpart else {
+            changes.push(Change::Removed { path: parent.child(i, Some(name)) });
+            continue;
+        };
+        matched[j] = true;
+        let new = &b[j];
+        let path = parent.child(j, Some(name));
+        match (old, new) {
+            (Item::Pair { op, value: Value::Block(x), trailing_comment: c, leading_comments: l, .. },
+             Item::Pair { op: new_op, value: Value::Block(y), trailing_comment: new_c, leading_comments: new_l, .. }) => {
+                if op != new_op || (!opts.ignore_comments && (c != new_c || l != new_l)) {
+                    changes.push(Change::Modified { path: path.clone(), old: Box::new(old.clone()), new: Box::new(new.clone()) });
+                }
+                diff_list(x, y, &path, opts, changes);
+            }
+            _ if !same(old, new, opts) => changes.push(Change::Modified { path, old: Box::new(old.clone()), new: Box::new(new.clone()) }),
+            _ => {}
+        }
+    }
+    // Unkeyed items: each one in `a` takes the first equal unmatched item in `b`
+    for (i, old) in a.iter().enumerate() {
+        if skipped(old, opts) { continue; }
+        match (0..b.len()).find(|&j| !matched[j] && !matches!(b[j], Item::Pair { .. }) && same(old, &b[j], opts)) {
+            Some(j) => matched[j] = true,
+            None => changes.push(Change::Removed { path: parent.child(i, None) }),
+        }
+    }
+    for (j, new) in b.iter().enumerate() {
+        if matched[j] || (skipped(new, opts) && !matches!(new, Item::Pair { .. })) { continue; }
+        let key = match new { Item::Pair { key, .. } => Some(key_text(key)), _ => None };
+        changes.push(Change::Added { path: parent.child(j, key) });
+    }
+}
+
+// Items left out of the unkeyed comparison: pairs (matched by key), blank lines, and comments when ignored
+fn skipped(item: &Item, opts: &DiffOptions) -> bool {
+    match item {
+        Item::Pair { .. } | Item::Blank(_) => true,
+        Item::Comment(_) => opts.ignore_comments,
+        Item::Value { .. } => false,
+    }
+}
+
+// Indices of the pairs in `items`, by key
+fn keyed(items: &[Item]) -> HashMap<String, Vec<usize>> {
+    let mut map: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, it) in items.iter().enumerate() {
+        if let Item::Pair { key, .. } = it { map.entry(key_text(key)).or_default().push(i); }
+    }
+    map
+}
+
+fn same(a: &Item, b: &Item, opts: &DiffOptions) -> bool {
+    if opts.ignore_comments { without_comments(a) == without_comments(b) } else { a == b }
+}
+
+// Copy of `item` with its own comments and any nested comment lines removed
+fn without_comments(item: &Item) -> Item {
+    let strip = |v: &Value| match v {
+        Value::Block(items) => Value::Block(items.iter().filter(|it| !matches!(it, Item::Comment(_))).map(without_comments).collect()),
+        _ => v.clone(),
+    };
+    match item {
+        Item::Pair { key, op, value, .. } => Item::Pair { key: key.clone(), op: op.clone(), value: strip(value), trailing_comment: None, leading_comments: Vec::new() },
+        Item::Value { value, .. } => Item::Value { value: strip(value), trailing_comment: None },
+        Item::Comment(_) | Item::Blank(_) => item.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    // Each change as `kind path`
+    fn changes(a: &str, b: &str, opts: &DiffOptions) -> Vec<String> {
+        diff_with(&parse_str(a).unwrap(), &parse_str(b).unwrap(), opts).iter().map(|c| match c {
+            Change::Added { path } => format!("added {}", path),
+            Change::Removed { path } => format!("removed {}", path),
+            Change::Modified { path, .. } => format!("modified {}", path),
+        }).collect()
+    }
+
+    #[test]
+    fn reports_keyed_changes_inside_blocks() {
+        let old = "idea = {\n  cost = 150\n  removal_cost = 10\n  modifier = { stability_factor = 0.1 }\n}\n";
+        let new = "idea = {\n  cost = 100\n  modifier = { stability_factor = 0.1 war_support_factor = 0.05 }\n}\n";
+        assert_eq!(changes(old, new, &DiffOptions::default()), ["modified idea.cost", "removed idea.removal_cost", "added idea.modifier.war_support_factor"]);
+        assert!(changes(old, old, &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn comments_can_be_ignored() {
+        let (old, new) = ("a = 1 # old\n# note\n", "a = 1 # new\n");
+        assert_eq!(changes(old, new, &DiffOptions::default()), ["modified a", "removed [1]"]);
+        assert!(changes(old, new, &DiffOptions { ignore_comments: true }).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::error::ParseError;
+use crate::{parse_str, Item};
+
+// Parse every `.txt` file under `root` in parallel. A file that cannot be read or parsed
+// only fails its own entry. Results are sorted by path; an unreadable directory is skipped.
+pub fn parse_dir(root: &Path) -> Vec<(PathBuf, Result<Vec<Item>, ParseError>)> {
+    let mut files = Vec::new();
+    collect_txt(root, &mut files);
+    files.sort();
+    files.into_par_iter()
+        .map(|path| {
+            let result = fs::read_to_string(&path).map_err(ParseError::from).and_then(|s| parse_str(&s));
+            (path, result)
+        })
+        .collect()
+}
+
+fn collect_txt(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        // file_type does not follow symlinks, so a linked directory (possibly a cycle back up the tree)
+        // is never entered; a linked file is still read
+        if file_type.is_dir() {
+            collect_txt(&path, out);
+        } else if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("txt")) && path.is_file() {
+            out.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn parses_every_txt_file_under_the_root() {
+        let root = env::temp_dir().join(format!("clausewitz-parse-dir-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("common/ideas")).unwrap();
+        fs::write(root.join("a.txt"), "a = 1").unwrap();
+        fs::write(root.join("common/b.TXT"), "b = { c = 2 }").unwrap();
+        fs::write(root.join("common/ideas/c.txt"), "c = yes").unwrap();
+        fs::write(root.join("common/broken.txt"), "d = {").unwrap();
+        fs::write(root.join("readme.md"), "not script").unwrap();
+        // A link back to the root must not be followed
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("common/ideas/loop")).unwrap();
+
+        let results = parse_dir(&root);
+        fs::remove_dir_all(&root).unwrap();
+        let names: Vec<String> = results.iter().map(|(p, _)| p.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(names, ["a.txt", "common/b.TXT", "common/broken.txt", "common/ideas/c.txt"]);
+        assert_eq!(results.iter().filter(|(_, r)| r.is_ok()).count(), 3);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{key_name, Atom, Item, KeyAtom, Value};
+
+// Which identifiers a rename touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenameScope {
+    #[default]
+    All,
+    Keys,
+    Values,
+}
+
+// Rename every identifier key and value equal to `old`; returns the number of replacements
+pub fn rename_identifier(items: &mut [Item], old: &str, new: &str) -> usize {
+    rename_identifier_in(items, old, new, RenameScope::All)
+}
+
+// Rename identifiers equal to `old`, limited to keys or values by `scope`
+pub fn rename_identifier_in(items: &mut [Item], old: &str, new: &str, scope: RenameScope) -> usize {
+    let mut count = 0;
+    for it in items.iter_mut() {
+        match it {
+            Item::Pair { key, value, .. } => {
+                if scope != RenameScope::Values {
+                    if let KeyAtom::Ident(s) = key {
+                        if s == old { *s = new.to_string(); count += 1; }
+                    }
+                }
+                count += rename_in_value(value, old, new, scope);
+            }
+            Item::Value { value, .. } => count += rename_in_value(value, old, new, scope),
+            Item::Comment(_) | Item::Blank(_) => {}
+        }
+    }
+    count
+}
+
+// Rename inside a value, recursing into nested blocks
+fn rename_in_value(v: &mut Value, old: &str, new: &str, scope: RenameScope) -> usize {
+    match v {
+        Value::Atom(a) if scope != RenameScope::Keys => rename_atom(a, old, new),
+        Value::Array(arr) if scope != RenameScope::Keys => arr.iter_mut().map(|a| rename_atom(a, old, new)).sum(),
+        Value::Block(items) => rename_identifier_in(items, old, new, scope),
+        _ => 0,
+    }
+}
+
+fn rename_atom(a: &mut Atom, old: &str, new: &str) -> usize {
+    match a {
+        Atom::Ident(s) if s == old => { *s = new.to_string(); 1 }
+        _ => 0,
+    }
+}
+
+// Keep only the pairs whose key is in `keep`, in their original order; comments and bare values are dropped.
+// Keys are named without quotes, as in get_path.
+pub fn project(items: &[Item], keep: &[&str]) -> Vec<Item> {
+    items.iter()
+        .filter(|it| matches!(it, Item::Pair { key, .. } if keep.contains(&key_name(key).as_ref())))
+        .cloned()
+        .collect()
+}
+
+// Keep only the given dotted paths (`"focus.id"`); a path naming a pair keeps it whole,
+// a longer path keeps the pair but projects its block down to the rest of the path
+pub fn project_deep(items: &[Item], paths: &[&str]) -> Vec<Item> {
+    let mut out = Vec::new();
+    for it in items {
+        let Item::Pair { key, op, value, trailing_comment, leading_comments } = it else { continue };
+        let name = key_name(key);
+        let mut whole = false;
+        let mut rest: Vec<&str> = Vec::new();
+        for p in paths {
+            match p.split_once('.') {
+                None if *p == name => whole = true,
+                Some((head, tail)) if head == name => rest.push(tail),
+                _ => {}
+            }
+        }
+        if whole {
+            out.push(it.clone());
+        } else if let (false, Value::Block(inner)) = (rest.is_empty(), value) {
+            let value = Value::Block(project_deep(inner, &rest));
+            out.push(Item::Pair { key: key.clone(), op: op.clone(), value, trailing_comment: trailing_comment.clone(), leading_comments: leading_comments.clone() });
+        }
+    }
+    out
+}
+
+// Move each run of comment lines directly above a pair into the pair's `leading_comments`,
+// recursing into nested blocks, so sorting or merging keeps a comment with the key it documents.
+// A blank line between the comments and the pair, or a bare value after them, leaves them standalone.
+pub fn attach_comments(items: &mut Vec<Item>) {
+    let mut out = Vec::with_capacity(items.len());
+    let mut run: Vec<String> = Vec::new();
+    for mut it in items.drain(..) {
+        match &mut it {
+            Item::Comment(c) => { run.push(std::mem::take(c)); continue; }
+            Item::Pair { leading_comments, value, .. } => {
+                leading_comments.splice(0..0, run.drain(..));
+                if let Value::Block(inner) = value { attach_comments(inner); }
+            }
+            Item::Value { value: Value::Block(inner), .. } => attach_comments(inner),
+            _ => {}
+        }
+        out.extend(run.drain(..).map(Item::Comment));
+        out.push(it);
+    }
+    out.extend(run.into_iter().map(Item::Comment));
+    *items = out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, serialize_ast};
+
+    const SCRIPT: &str = "GER = { tag = GER allies = { ITA GER } }\n\"GER\" = \"GER\"\n";
+
+    fn renamed(scope: RenameScope) -> (usize, String) {
+        let mut items = parse_str(SCRIPT).unwrap();
+        let n = rename_identifier_in(&mut items, "GER", "DEU", scope);
+        (n, serialize_ast(&items))
+    }
+
+    #[test]
+    fn rename_touches_identifier_keys_and_values() {
+        let mut items = parse_str(SCRIPT).unwrap();
+        assert_eq!(rename_identifier(&mut items, "GER", "DEU"), 3);
+        // Quoted strings are text, not identifiers, and stay as they are
+        assert_eq!(items, parse_str("DEU = { tag = DEU allies = { ITA DEU } }\n\"GER\" = \"GER\"\n").unwrap());
+        assert_eq!(rename_identifier(&mut items, "GER", "DEU"), 0);
+    }
+
+    #[test]
+    fn rename_scope_limits_keys_or_values() {
+        assert_eq!(renamed(RenameScope::All), renamed(RenameScope::default()));
+        let (n, keys) = renamed(RenameScope::Keys);
+        assert_eq!(n, 1);
+        assert_eq!(parse_str(&keys).unwrap(), parse_str("DEU = { tag = GER allies = { ITA GER } }\n\"GER\" = \"GER\"\n").unwrap());
+        let (n, values) = renamed(RenameScope::Values);
+        assert_eq!(n, 2);
+        assert_eq!(parse_str(&values).unwrap(), parse_str("GER = { tag = DEU allies = { ITA DEU } }\n\"GER\" = \"GER\"\n").unwrap());
+    }
+
+
+    const FOCUS: &str = "# tree\nid = tree\nfocus = { id = a cost = 10 reward = { pp = 5 xp = 1 } }\nfocus = { id = b cost = 5 }\n\"quoted key\" = yes\n1 = number\n";
+
+    #[test]
+    fn project_keeps_named_pairs_in_order() {
+        let items = parse_str(FOCUS).unwrap();
+        let kept = project(&items, &["quoted key", "focus"]);
+        assert_eq!(kept, parse_str("focus = { id = a cost = 10 reward = { pp = 5 xp = 1 } }\nfocus = { id = b cost = 5 }\n\"quoted key\" = yes\n").unwrap());
+        assert_eq!(project(&items, &["1"]), parse_str("1 = number").unwrap());
+        assert!(project(&items, &["missing", "\"quoted key\""]).is_empty());
+    }
+
+    #[test]
+    fn project_deep_trims_blocks_to_the_rest_of_the_path() {
+        let items = parse_str(FOCUS).unwrap();
+        let kept = project_deep(&items, &["focus.id", "focus.reward.pp", "quoted key"]);
+        assert_eq!(kept, parse_str("focus = { id = a reward = { pp = 5 } }\nfocus = { id = b }\n\"quoted key\" = yes\n").unwrap());
+        // A whole pair wins over a longer path through it; a path into a non-block keeps nothing
+        assert_eq!(project_deep(&items, &["focus", "focus.id"]), project(&items, &["focus"]));
+        assert!(project_deep(&items, &["id.x", "missing.id"]).is_empty());
+    }
+
+
+    #[test]
+    fn attached_comments_travel_with_their_pair() {
+        let input = "# header\n\n# about b\n# more about b\nb = 2\nc = {\n\t# about d\n\td = 4\n}\n# loose\n";
+        let mut items = parse_str(input).unwrap();
+        attach_comments(&mut items);
+        let Item::Pair { leading_comments, .. } = &items[2] else { panic!("{:?}", items) };
+        assert_eq!(leading_comments.len(), 2);
+        assert!(matches!(&items[0], Item::Comment(_)));
+        assert!(matches!(items.last(), Some(Item::Comment(_))));
+        let Item::Pair { value: Value::Block(inner), .. } = &items[3] else { panic!("{:?}", items) };
+        assert!(matches!(&inner[0], Item::Pair { leading_comments, .. } if leading_comments.len() == 1));
+        // Sorting moves the comments along with `b`
+        items.swap(2, 3);
+        let out = serialize_ast(&items);
+        assert!(out.find("# about d").unwrap() < out.find("# about b").unwrap(), "{}", out);
+        assert!(out.contains("# about b\n# more about b\nb = 2\n"), "{}", out);
+        let mut reparsed = parse_str(&out).unwrap();
+        attach_comments(&mut reparsed);
+        assert_eq!(reparsed, items);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fmt, io};
+
+use pest::error::{Error, LineColLocation};
+
+use crate::Rule;
+
+// Which way the braces are unbalanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BraceImbalance {
+    // A `{` is never closed
+    Unclosed,
+    // A `}` has no matching `{`
+    Unexpected,
+}
+
+// Parse errors
+#[derive(Debug)]
+pub enum ParseError {
+    // Grammar error reported by pest
+    Pest(Box<Error<Rule>>),
+    // Brace mismatch found after a failed parse; `line` is 1-based
+    UnbalancedBraces { kind: BraceImbalance, line: usize },
+    // Date token with a missing or out-of-range component
+    InvalidDate { text: String, reason: &'static str, line: usize, col: usize },
+    // Number token that does not fit a finite f64
+    InvalidNumber { text: String, line: usize, col: usize },
+    // Color keyword followed by a block that is not 3 or 4 numbers (`rgb { 1 2 }`, or one holding a comment)
+    InvalidColor { line: usize, col: usize },
+    // Key directly followed by a block without an operator (`key { ... }`)
+    MissingOperator { key: String, line: usize, col: usize },
+    // Bare value in a list that also holds pairs, rejected by parse_str_strict
+    MixedBlock { value: String, line: usize, col: usize },
+    // Block nested deeper than ParseOptions::max_depth; `depth` is its nesting level and `span` its opening brace
+    TooDeep { depth: usize, span: Span },
+    // Failure reading or writing a file
+    Io(io::Error),
+    // JSON that does not describe an AST
+    Json(serde_json::Error),
+}
+
+impl From<Error<Rule>> for ParseError {
+    fn from(e: Error<Rule>) -> Self { ParseError::Pest(Box::new(e)) }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self { ParseError::Io(e) }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self { ParseError::Json(e) }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Pest(e) => Some(e.as_ref()),
+            ParseError::Io(e) => Some(e),
+            ParseError::Json(e) => Some(e),
+            ParseError::UnbalancedBraces { .. } | ParseError::InvalidDate { .. } | ParseError::InvalidNumber { .. }
+            | ParseError::InvalidColor { .. } | ParseError::MissingOperator { .. } | ParseError::MixedBlock { .. } | ParseError::TooDeep { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Pest(e) => {
+                let (line, col) = match e.line_col { LineColLocation::Pos(p) => p, LineColLocation::Span(p, _) => p };
+                write!(f, "parse error at line {}, col {}: {}", line, col, e.variant.message())
+            }
+            ParseError::UnbalancedBraces { kind: BraceImbalance::Unclosed, line } => write!(f, "unclosed block opened at line {}", line),
+            ParseError::UnbalancedBraces { kind: BraceImbalance::Unexpected, line } => write!(f, "unexpected '}}' at line {}", line),
+            ParseError::InvalidDate { text, reason, line, col } => write!(f, "invalid date '{}' at line {}, col {}: {}", text, line, col, reason),
+            ParseError::InvalidNumber { text, line, col } => write!(f, "invalid number '{}' at line {}, col {}", text, line, col),
+            ParseError::InvalidColor { line, col } => write!(f, "invalid color at line {}, col {}: expected 3 or 4 numbers and no comments", line, col),
+            ParseError::MissingOperator { key, line, col } => write!(f, "missing operator between key '{}' and its block at line {}, col {}", key, line, col),
+            ParseError::MixedBlock { value, line, col } => write!(f, "bare value '{}' mixed with key/value pairs at line {}, col {}", value, line, col),
+            ParseError::TooDeep { depth, span } => write!(f, "block at byte {} is nested {} levels deep, past the nesting limit", span.start, depth),
+            ParseError::Io(e) => write!(f, "io error: {}", e),
+            ParseError::Json(e) => write!(f, "json error: {}", e),
+        }
+    }
+}
+
+// Byte range in the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span { pub start: usize, pub end: usize }
+
+// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity { Error, Warning }
+
+// A problem found while parsing, located by byte span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic { pub span: Span, pub message: String, pub severity: Severity }
@@ -0,0 +1,54 @@
+use crate::error::{Diagnostic, Severity};
+use crate::{parse_str_recover, serialize_ast_with, SerializeOptions};
+
+// Options for format_str
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    pub serialize: SerializeOptions,
+}
+
+// Parse and re-serialize script text. Running it again on its own output gives the same text.
+// Accepts exactly what parse_str does, and fails with every diagnostic found otherwise.
+pub fn format_str(input: &str, opts: &FormatOptions) -> Result<String, Vec<Diagnostic>> {
+    let (items, mut diagnostics) = parse_str_recover(input);
+    if !diagnostics.is_empty() {
+        // A key missing its operator only warns while recovering, but parse_str rejects it; so does formatting
+        for d in &mut diagnostics { d.severity = Severity::Error; }
+        return Err(diagnostics);
+    }
+    Ok(serialize_ast_with(&items, &opts.serialize))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{Indent, LineEnding};
+
+    #[test]
+    fn formatting_is_idempotent_over_the_fixtures() {
+        let option_sets = [
+            FormatOptions::default(),
+            FormatOptions { serialize: SerializeOptions { indent: Indent::Tabs, align_operators: true, sort_keys: true, ..SerializeOptions::default() } },
+            FormatOptions { serialize: SerializeOptions { inline_blocks_under: Some(60), array_wrap_width: 20, line_ending: LineEnding::Crlf, ..SerializeOptions::default() } },
+        ];
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        for entry in fs::read_dir(dir).unwrap() {
+            let input = fs::read_to_string(entry.unwrap().path()).unwrap();
+            for opts in &option_sets {
+                let once = format_str(&input, opts).unwrap();
+                assert_eq!(format_str(&once, opts).unwrap(), once);
+            }
+        }
+    }
+
+    #[test]
+    fn a_missing_operator_fails_like_parse_str() {
+        assert!(crate::parse_str("x { a = 1 }").is_err());
+        let diagnostics = format_str("x { a = 1 }", &FormatOptions::default()).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(diagnostics[0].message.starts_with("missing operator"), "{}", diagnostics[0].message);
+    }
+}
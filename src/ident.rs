@@ -0,0 +1,66 @@
+// Kind of special syntax carried by an identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentKind<'a> {
+    // `@name`: scripted variable reference
+    Variable(&'a str),
+    // Contains `$NAME$` parameter placeholders; holds the parameter names in order.
+    // Takes precedence over the other kinds, since the final text is only known after substitution.
+    Parameter(Vec<&'a str>),
+    // `prefix:target`, e.g. `scope:attacker` or `var:global.x`; the target is split on `.`
+    Scoped { prefix: &'a str, path: Vec<&'a str> },
+    Plain,
+}
+
+// Classify an identifier by its variable/parameter/scope syntax
+pub fn classify_identifier(s: &str) -> IdentKind<'_> {
+    let params = parameters(s);
+    if !params.is_empty() { return IdentKind::Parameter(params); }
+    if let Some(name) = s.strip_prefix('@') { return IdentKind::Variable(name); }
+    if let Some((prefix, target)) = s.split_once(':') {
+        if !prefix.is_empty() && !target.is_empty() {
+            return IdentKind::Scoped { prefix, path: target.split('.').collect() };
+        }
+    }
+    IdentKind::Plain
+}
+
+// Names between pairs of `$`, skipping empty `$$`
+fn parameters(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('$') else { break };
+        if end > 0 { out.push(&after[..end]); }
+        rest = &after[end + 1..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_identifier_syntax() {
+        use IdentKind::*;
+        let cases: &[(&str, IdentKind)] = &[
+            ("GER", Plain),
+            ("@cost", Variable("cost")),
+            ("@", Variable("")),
+            ("scope:attacker", Scoped { prefix: "scope", path: vec!["attacker"] }),
+            ("var:global.x", Scoped { prefix: "var", path: vec!["global", "x"] }),
+            (":x", Plain),
+            ("x:", Plain),
+            ("$TAG$", Parameter(vec!["TAG"])),
+            ("$TAG$_$ID$_focus", Parameter(vec!["TAG", "ID"])),
+            ("var:$TAG$.x", Parameter(vec!["TAG"])),
+            ("@$NAME$", Parameter(vec!["NAME"])),
+            ("a$$b", Plain),
+            ("$open", Plain),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(&classify_identifier(input), expected, "{}", input);
+        }
+    }
+}
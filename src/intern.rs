@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::ParseError;
+use crate::{parse_items, parse_str, Atom, Boolean, ColorSpace, Date, Item, KeyAtom, Number, Operator, Value};
+
+// Identifier or string text shared by every equal occurrence
+pub type Symbol = Arc<str>;
+
+// Pool of interned text; reuse one across files to share storage between them too
+#[derive(Debug, Default)]
+pub struct Interner { pool: HashSet<Symbol> }
+
+impl Interner {
+    pub fn new() -> Self { Self::default() }
+
+    // The shared copy of `s`, added to the pool on first use
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(sym) = self.pool.get(s) { return sym.clone(); }
+        let sym: Symbol = Arc::from(s);
+        self.pool.insert(sym.clone());
+        sym
+    }
+
+    // Number of distinct strings in the pool
+    pub fn len(&self) -> usize { self.pool.len() }
+
+    pub fn is_empty(&self) -> bool { self.pool.is_empty() }
+}
+
+// Interned counterparts of the AST types: identifiers and strings are Symbols, the rest is unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternedAtom { String(Symbol), Ident(Symbol), Number(Number), Date(Date), Bool(Boolean) }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternedKey { Ident(Symbol), Number(Number), Date(Date), Quoted(Symbol) }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternedValue {
+    Atom(InternedAtom),
+    Array(Vec<InternedAtom>),
+    Color { space: ColorSpace, components: Vec<Number> },
+    Block(Vec<InternedItem>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternedItem {
+    Pair { key: InternedKey, op: Operator, value: InternedValue, trailing_comment: Option<String>, leading_comments: Vec<String> },
+    Value { value: InternedValue, trailing_comment: Option<String> },
+    Comment(String),
+    Blank(u8),
+}
+
+// Parse with a fresh pool, so equal identifiers and strings in the file share one allocation
+pub fn parse_str_interned(input: &str) -> Result<Vec<InternedItem>, ParseError> {
+    parse_str_interned_with(input, &mut Interner::new())
+}
+
+// Parse, interning into `pool`. Items are converted one top-level item at a time,
+// so the owned AST of the whole file never exists at once.
+pub fn parse_str_interned_with(input: &str, pool: &mut Interner) -> Result<Vec<InternedItem>, ParseError> {
+    let mut items = Vec::new();
+    for result in parse_items(input) {
+        match result {
+            Ok(item) => items.push(intern_item(item, pool)),
+            // Report the error exactly as parse_str would
+            Err(e) => return Err(parse_str(input).err().unwrap_or(e)),
+        }
+    }
+    Ok(items)
+}
+
+fn intern_item(item: Item, pool: &mut Interner) -> InternedItem {
+    match item {
+        Item::Pair { key, op, value, trailing_comment, leading_comments } => {
+            InternedItem::Pair { key: intern_key(key, pool), op, value: intern_value(value, pool), trailing_comment, leading_comments }
+        }
+        Item::Value { value, trailing_comment } => InternedItem::Value { value: intern_value(value, pool), trailing_comment },
+        Item::Comment(c) => InternedItem::Comment(c),
+        Item::Blank(n) => InternedItem::Blank(n),
+    }
+}
+
+fn intern_key(key: KeyAtom, pool: &mut Interner) -> InternedKey {
+    match key {
+        KeyAtom::Ident(s) => InternedKey::Ident(pool.intern(&s)),
+        KeyAtom::Number(n) => InternedKey::Number(n),
+        KeyAtom::Date(d) => InternedKey::Date(d),
+        KeyAtom::Quoted(s) => InternedKey::Quoted(pool.intern(&s)),
+    }
+}
+
+fn intern_value(value: Value, pool: &mut Interner) -> InternedValue {
+    match value {
+        Value::Atom(a) => InternedValue::Atom(intern_atom(a, pool)),
+        Value::Array(arr) => InternedValue::Array(arr.into_iter().map(|a| intern_atom(a, pool)).collect()),
+        Value::Color { space, components } => InternedValue::Color { space, components },
+        Value::Block(items) => InternedValue::Block(items.into_iter().map(|it| intern_item(it, pool)).collect()),
+    }
+}
+
+fn intern_atom(atom: Atom, pool: &mut Interner) -> InternedAtom {
+    match atom {
+        Atom::String(s) => InternedAtom::String(pool.intern(&s)),
+        Atom::Ident(s) => InternedAtom::Ident(pool.intern(&s)),
+        Atom::Number(n) => InternedAtom::Number(n),
+        Atom::Date(d) => InternedAtom::Date(d),
+        Atom::Bool(b) => InternedAtom::Bool(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_symbol(item: &InternedItem) -> &Symbol {
+        match item { InternedItem::Pair { key: InternedKey::Ident(s), .. } => s, other => panic!("not an identifier pair: {:?}", other) }
+    }
+
+    #[test]
+    fn equal_identifiers_share_one_allocation() {
+        let items = parse_str_interned("cost = 1\ncost = 2\nname = cost\n").unwrap();
+        assert!(Arc::ptr_eq(key_symbol(&items[0]), key_symbol(&items[1])));
+        let InternedItem::Pair { value: InternedValue::Atom(InternedAtom::Ident(v)), .. } = &items[2] else { panic!() };
+        assert!(Arc::ptr_eq(key_symbol(&items[0]), v));
+    }
+
+    #[test]
+    fn a_shared_pool_spans_files() {
+        let mut pool = Interner::new();
+        let a = parse_str_interned_with("stability_factor = 0.1", &mut pool).unwrap();
+        let b = parse_str_interned_with("stability_factor = 0.2", &mut pool).unwrap();
+        assert!(Arc::ptr_eq(key_symbol(&a[0]), key_symbol(&b[0])));
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+use serde::Deserialize;
+
+use crate::error::ParseError;
+use crate::Item;
+
+// JSON form of an AST, using the serde derives. Numbers keep their literal text and
+// floats are read back exactly, so from_json(&to_json(items)) gives the same items.
+pub fn to_json(items: &[Item]) -> serde_json::Value {
+    serde_json::to_value(items).expect("AST always converts to JSON")
+}
+
+// Read an AST back from the JSON made by to_json
+pub fn from_json(v: &serde_json::Value) -> Result<Vec<Item>, ParseError> {
+    Ok(Vec::<Item>::deserialize(v)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::parse_str;
+
+    // The JSON of the only pair's value
+    fn value_json(input: &str) -> serde_json::Value {
+        to_json(&parse_str(input).unwrap())[0]["Pair"]["value"].clone()
+    }
+
+    #[test]
+    fn json_shape_of_edge_cases() {
+        assert_eq!(value_json("a = {}"), json!({ "Array": [] }));
+        assert_eq!(value_json("a = { b = { c = 1 } }")["Block"][0]["Pair"]["value"]["Block"][0]["Pair"]["key"], json!({ "Ident": "c" }));
+        assert_eq!(
+            value_json("a = 1936.1.1.12"),
+            json!({ "Atom": { "Date": { "y": 1936, "m": 1, "d": 1, "h": 12, "min": null, "sec": null, "quoted": false, "literal": null } } })
+        );
+        assert_eq!(value_json("a = 1.50")["Atom"]["Number"], json!({ "value": 1.5, "literal": "1.50" }));
+    }
+
+    #[test]
+    fn ast_round_trips_through_json() {
+        let input = "# header\na = {}\nb = { c = { d = -0 e = 1.50 } }\n\n\"1939.9.1.12\" = { war = YES }\nf = 1936.01.01.12.30.00\ng = \"say \\\"hi\\\"\" # note\nh = hsv { 0.5 1.0 1 }\ni = { 1 2 three }\nj ?= 2E6\n";
+        let items = parse_str(input).unwrap();
+        let back = from_json(&to_json(&items)).unwrap();
+        assert_eq!(back, items);
+        // Formatting details survive too, not just what PartialEq compares
+        assert_eq!(crate::serialize_ast(&back), crate::serialize_ast(&items));
+        assert!(matches!(from_json(&json!([{ "Pair": { "key": 1 } }])), Err(ParseError::Json(_))));
+    }
+}
@@ -0,0 +1,1656 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use pest::Parser;
+use pest::iterators::{Pair, Pairs};
+use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
+
+mod borrowed;
+mod builder;
+#[cfg(feature = "chrono")]
+mod calendar;
+mod color;
+#[cfg(feature = "rayon")]
+mod dir;
+mod diff;
+mod edit;
+mod error;
+mod format;
+mod ident;
+#[cfg(feature = "intern")]
+mod intern;
+mod json;
+mod lint;
+mod locate;
+mod merge;
+mod patch;
+mod path;
+mod query;
+mod recover;
+mod scan;
+mod stats;
+mod string_utils;
+mod visit;
+
+pub use borrowed::{parse_str_borrowed, AtomRef, ItemRef, KeyRef, ValueRef};
+pub use builder::BlockBuilder;
+pub use color::{convert_color, normalize_colors};
+#[cfg(feature = "rayon")]
+pub use dir::parse_dir;
+pub use diff::{diff, diff_with, Change, DiffOptions};
+pub use edit::{attach_comments, project, project_deep, rename_identifier, rename_identifier_in, RenameScope};
+pub use error::{BraceImbalance, Diagnostic, ParseError, Severity, Span};
+pub use format::{format_str, FormatOptions};
+pub use ident::{classify_identifier, IdentKind};
+#[cfg(feature = "intern")]
+pub use intern::{parse_str_interned, parse_str_interned_with, InternedAtom, InternedItem, InternedKey, InternedValue, Interner, Symbol};
+pub use json::{from_json, to_json};
+pub use lint::{duplicate_keys_in_block, find_duplicate_keys, find_orphan_values};
+pub use locate::node_at;
+pub use merge::merge;
+pub use patch::{apply_patch, to_patch, Patch, PatchOp};
+pub use path::{NodePath, PathSegment};
+pub use query::{get_path, pairs, values_of};
+pub use recover::{parse_items, parse_str_all_errors, parse_str_lenient, parse_str_recover, PartialParse};
+pub use stats::{stats, AstStats};
+pub use string_utils::{escape_string, escape_string_ascii, escape_string_with, unescape_string, unescape_string_with, EscapeConfig, ESCAPE_SEQUENCES};
+pub use visit::{walk, walk_mut, Visitor, VisitorMut};
+
+use scan::{check_braces, deep_braces, too_deep};
+
+// Byte order mark some editors put at the start of script files
+pub(crate) const BOM: char = '\u{feff}';
+
+// Derive parser for Paradox script grammar
+#[derive(Parser)]
+#[grammar = "hoi4.pest"]
+pub(crate) struct HoiParser;
+
+// Operator types (assignment, optional assignment `?=` and comparisons)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operator { Eq, Ne, OptEq, Le, Ge, Lt, Gt }
+
+// Date type (YYYY.MM.DD(.HH(.MM(.SS)))); `quoted` records whether it was written as "YYYY.MM.DD".
+// Minutes are only present with an hour, and seconds with minutes. `literal` keeps the unquoted source text
+// when its digits differ from how the parts print (`1936.01.01`, `1936.1.1.12.5`), so every part keeps the width it was read with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Date { pub y: u32, pub m: u8, pub d: u8, pub h: Option<u8>, pub min: Option<u8>, pub sec: Option<u8>, pub quoted: bool, pub literal: Option<String> }
+
+// Like numbers, dates compare by their parts and `quoted`; `literal` is formatting: `1936.01.01 == 1936.1.1`
+impl PartialEq for Date {
+    fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+impl Eq for Date {}
+
+// Chronological order, missing time parts counting as 0. Ties between equal moments
+// (`1936.1.1` and `1936.1.1.0`, quoted or not) are broken by which parts are present and then `quoted`, to agree with Eq.
+impl Ord for Date {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key = |d: &Date| (d.y, d.m, d.d, d.h.unwrap_or(0), d.min.unwrap_or(0), d.sec.unwrap_or(0), d.h, d.min, d.sec, d.quoted);
+        key(self).cmp(&key(other))
+    }
+}
+
+impl PartialOrd for Date {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+// Number value; `literal` keeps the source text when it differs from how `value` prints (`+3`, `1.50`, `2E6`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Number { pub value: f64, pub literal: Option<String> }
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self { Number { value, literal: None } }
+}
+
+// Numbers compare by the bits of `value`, which makes equality total (so the AST can be Eq);
+// parsed numbers are always finite, and 0 and -0 differ. `literal` is formatting and is ignored: `+3 == 3`.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool { self.value.to_bits() == other.value.to_bits() }
+}
+
+impl Eq for Number {}
+
+// Boolean value; `literal` keeps the source text when it is not the lowercase `yes`/`no` (`YES`, `No`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Boolean { pub value: bool, pub literal: Option<String> }
+
+impl From<bool> for Boolean {
+    fn from(value: bool) -> Self { Boolean { value, literal: None } }
+}
+
+// Like numbers, booleans compare by value only: `YES == yes`
+impl PartialEq for Boolean {
+    fn eq(&self, other: &Self) -> bool { self.value == other.value }
+}
+
+impl Eq for Boolean {}
+
+// Atomic values: string (stored unescaped), identifier, number, date, boolean
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Atom { String(String), Ident(String), Number(Number), Date(Date), Bool(Boolean) }
+
+// Key types: identifier, number, date, or quoted string (stored unescaped)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAtom { Ident(String), Number(Number), Date(Date), Quoted(String) }
+
+// Color spaces: rgb { r g b } (0-255), hsv { h s v } (0-1), hsv360 { h s v } (0-360 / 0-100).
+// These are the three keywords the games write; rgb is always on the 0-255 scale, so there is no separate Rgb255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace { Rgb, Hsv, Hsv360 }
+
+// Value types: atom, array (plain value list), color, or block (with key/values and comments).
+// Which of array and block a `{ ... }` becomes is decided by Value::from_items.
+// Color components are Numbers, so they keep their literal text (`0.50`) like any other number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Value { Atom(Atom), Array(Vec<Atom>), Color { space: ColorSpace, components: Vec<Number> }, Block(Vec<Item>) }
+
+impl Value {
+    // True for an array or block with no elements (`{}`); atoms and colors are never empty
+    pub fn is_empty(&self) -> bool {
+        match self { Value::Array(arr) => arr.is_empty(), Value::Block(items) => items.is_empty(), _ => false }
+    }
+
+    // The container the parser makes for these items: an Array when every item is a plain atom
+    // value with no trailing comment (including no items at all), a Block otherwise.
+    // Build containers through this to get the same value, and output, as parsing the text would.
+    pub fn from_items(items: Vec<Item>) -> Value {
+        let only_atoms = items.iter().all(|it| matches!(it, Item::Value { value: Value::Atom(_), trailing_comment: None }));
+        if !only_atoms { return Value::Block(items); }
+        let mut atoms = Vec::with_capacity(items.len());
+        atoms.extend(items.into_iter().filter_map(|it| match it { Item::Value { value: Value::Atom(a), .. } => Some(a), _ => None }));
+        Value::Array(atoms)
+    }
+}
+
+// Item: key-value pair, standalone value, comment, or a run of blank lines.
+// A comment on the same line after a pair or value is kept with it as `trailing_comment`.
+// `leading_comments` are comment lines written directly above a pair; the parser leaves them
+// as Comment items, and attach_comments moves them into the pair so they travel with it.
+// Blank lines are only recorded between top-level items, so a grouped file keeps its spacing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Item {
+    Pair { key: KeyAtom, op: Operator, value: Value, trailing_comment: Option<String>, leading_comments: Vec<String> },
+    Value { value: Value, trailing_comment: Option<String> },
+    Comment(String),
+    Blank(u8),
+}
+
+// Quoting policy for dates used as keys (date values always keep their original quoting)
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DateQuoting {
+    // Quote only the keys that were quoted when read
+    #[default]
+    Preserve,
+    Always,
+    Never,
+}
+
+// Line terminator written by the serializer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    // The ending used by most lines of `input` (Lf when it has none), to write a file back the way it was read
+    pub fn detect(input: &str) -> LineEnding {
+        let lines = input.matches('\n').count();
+        let crlf = input.matches("\r\n").count();
+        if crlf * 2 > lines { LineEnding::Crlf } else { LineEnding::Lf }
+    }
+}
+
+// Indentation unit for nested blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Default for Indent {
+    fn default() -> Self { Indent::Spaces(2) }
+}
+
+// Serializer options
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    // Indentation per nesting level (default: two spaces)
+    pub indent: Indent,
+    pub date_key_quoting: DateQuoting,
+    // Rewrite comments as `# text` (one space after the `#` run); off keeps them verbatim
+    pub normalize_comments: bool,
+    // Wrap array lines longer than this (excluding indentation); usize::MAX keeps arrays on one line.
+    // A list containing comments is a block, not an array, so each comment keeps its own line.
+    pub array_wrap_width: usize,
+    // Emit a block or array on one line (`{ has_dlc = yes }`) when that rendering is shorter than this.
+    // Containers with comments, and those that do not fit, expand as usual.
+    pub inline_blocks_under: Option<usize>,
+    // Pad keys so the operators of a block's scalar pairs line up; block- and array-valued pairs are not padded
+    pub align_operators: bool,
+    // Replacement text for operators, for consumers expecting a non-standard spelling; unlisted operators use the standard text
+    pub operator_overrides: Vec<(Operator, String)>,
+    // Stably sort the pairs of every block (and the file) by key text, recursively. Pairs are
+    // reordered among the positions pairs occupy, so comments and bare values stay where they are.
+    pub sort_keys: bool,
+    // End non-empty output with exactly one newline; off ends it at the last character of the last item
+    pub final_newline: bool,
+    // Line terminator for every line; pair with LineEnding::detect to keep a file's own endings
+    pub line_ending: LineEnding,
+}
+
+// Parser options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    // Deepest block nesting accepted; deeper input fails with ParseError::TooDeep instead of exhausting the stack
+    pub max_depth: usize,
+}
+
+// Parsing recurses once per level; about 150 levels fit the 2 MiB stack of a spawned thread in a debug build,
+// so the default leaves room for the caller's own frames. Real game files nest less than 20 deep.
+impl Default for ParseOptions {
+    fn default() -> Self { ParseOptions { max_depth: 100 } }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            indent: Indent::default(),
+            date_key_quoting: DateQuoting::default(),
+            normalize_comments: false,
+            array_wrap_width: 120,
+            inline_blocks_under: None,
+            align_operators: false,
+            operator_overrides: Vec::new(),
+            sort_keys: false,
+            final_newline: true,
+            line_ending: LineEnding::default(),
+        }
+    }
+}
+
+// Days per month in the Clausewitz calendar (no leap years)
+const DAYS_IN_MONTH: [u8; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+// Parse and range-check a date string (YYYY.MM.DD(.HH(.MM(.SS)))); the error names the bad component
+fn parse_date_str(s: &str) -> Result<Date, &'static str> {
+    let parts: Vec<&str> = s.split('.').collect();
+    if parts.len() < 3 || parts.iter().any(|x| x.is_empty()) { return Err("missing component"); }
+    if parts.len() > 6 { return Err("too many components"); }
+    let y = parts[0].parse::<u32>().map_err(|_| "invalid year")?;
+    let m = parts[1].parse::<u8>().map_err(|_| "invalid month")?;
+    let d = parts[2].parse::<u8>().map_err(|_| "invalid day")?;
+    let time = |i: usize, err| parts.get(i).map(|x| x.parse::<u8>().map_err(|_| err)).transpose();
+    let (h, min, sec) = (time(3, "invalid hour")?, time(4, "invalid minute")?, time(5, "invalid second")?);
+    if !(1..=12).contains(&m) { return Err("month out of range"); }
+    if d == 0 || d > DAYS_IN_MONTH[m as usize - 1] { return Err("day out of range"); }
+    if h.is_some_and(|h| h > 23) { return Err("hour out of range"); }
+    if min.is_some_and(|min| min > 59) { return Err("minute out of range"); }
+    if sec.is_some_and(|sec| sec > 59) { return Err("second out of range"); }
+    let date = Date { y, m, d, h, min, sec, quoted: false, literal: None };
+    let literal = Some(s.to_string()).filter(|s| *s != fmt_date(&date));
+    Ok(Date { literal, ..date })
+}
+
+// Parse a date token, locating any range error at the token
+pub(crate) fn parse_date(p: &Pair<Rule>) -> Result<Date, ParseError> {
+    parse_date_str(p.as_str()).map_err(|reason| {
+        let (line, col) = p.as_span().start_pos().line_col();
+        ParseError::InvalidDate { text: p.as_str().to_string(), reason, line, col }
+    })
+}
+
+// Parse a number token; literals f64 cannot represent (or that overflow to infinity) are errors
+pub(crate) fn parse_number(p: &Pair<Rule>) -> Result<f64, ParseError> {
+    match p.as_str().parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => {
+            let (line, col) = p.as_span().start_pos().line_col();
+            Err(ParseError::InvalidNumber { text: p.as_str().to_string(), line, col })
+        }
+    }
+}
+
+// Parse number token, keeping its literal text when printing the value would not reproduce it
+fn parse_number_literal(p: &Pair<Rule>) -> Result<Number, ParseError> {
+    let value = parse_number(p)?;
+    let literal = if fmt_f64(value) == p.as_str() { None } else { Some(p.as_str().to_string()) };
+    Ok(Number { value, literal })
+}
+
+// Parse a boolean, whatever its case, keeping the text when it is not lowercase
+fn parse_boolean(p: &Pair<Rule>) -> Boolean {
+    let value = p.as_str().eq_ignore_ascii_case("yes");
+    let literal = if p.as_str() == fmt_bool(value) { None } else { Some(p.as_str().to_string()) };
+    Boolean { value, literal }
+}
+
+// Parse operator
+pub(crate) fn parse_operator(p: Pair<Rule>) -> Operator {
+    match p.as_str() {
+        "=" => Operator::Eq,
+        "!=" => Operator::Ne,
+        "?=" => Operator::OptEq,
+        "<=" => Operator::Le,
+        ">=" => Operator::Ge,
+        "<" => Operator::Lt,
+        ">" => Operator::Gt,
+        _ => Operator::Eq,
+    }
+}
+
+// Parse atomic value
+fn parse_atom(p: Pair<Rule>) -> Result<Atom, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::string => {
+            let inner = p.into_inner().next().unwrap();
+            let s = inner.as_str();
+            // Recognize date-like pattern (YYYY.MM.DD(.HH)) within quoted strings
+            if let Some(d) = try_parse_date_like(s) { return Ok(Atom::Date(Date { quoted: true, ..d })); }
+            Atom::String(unescape_string(s))
+        }
+        Rule::identifier => Atom::Ident(p.as_str().to_string()),
+        Rule::number => Atom::Number(parse_number_literal(&p)?),
+        Rule::date => Atom::Date(parse_date(&p)?),
+        Rule::boolean => Atom::Bool(parse_boolean(&p)),
+        _ => Atom::Ident(p.as_str().to_string()),
+    })
+}
+
+// Parse key
+pub(crate) fn parse_key(p: Pair<Rule>) -> Result<KeyAtom, ParseError> {
+    Ok(match p.as_rule() {
+        // Key is a wrapper node; unwrap into the concrete token
+        Rule::key => parse_key(p.into_inner().next().unwrap())?,
+        Rule::identifier => KeyAtom::Ident(p.as_str().to_string()),
+        Rule::number => KeyAtom::Number(parse_number_literal(&p)?),
+        Rule::date => KeyAtom::Date(parse_date(&p)?),
+        Rule::quoted_date => {
+            let inner = p.into_inner().next().unwrap();
+            KeyAtom::Date(Date { quoted: true, ..parse_date(&inner)? })
+        }
+        Rule::string => KeyAtom::Quoted(unescape_string(p.into_inner().next().unwrap().as_str())),
+        _ => KeyAtom::Ident(p.as_str().to_string()),
+    })
+}
+
+// Atom for text the grammar reads as one bare token (identifier, number, date or boolean),
+// so values built in code compare equal to parsed ones; None for anything else
+pub(crate) fn bare_atom(s: &str) -> Option<Atom> {
+    let p = whole_token(Rule::value, s)?;
+    match p.as_rule() { Rule::identifier | Rule::number | Rule::date | Rule::boolean => parse_atom(p).ok(), _ => None }
+}
+
+// Key for text the grammar reads as one bare key token (identifier, number or date)
+pub(crate) fn bare_key(s: &str) -> Option<KeyAtom> {
+    let p = whole_token(Rule::key, s)?;
+    match p.as_rule() { Rule::identifier | Rule::number | Rule::date => parse_key(p).ok(), _ => None }
+}
+
+// The token inside a value or key wrapper, when it spans all of `s`
+fn whole_token(rule: Rule, s: &str) -> Option<Pair<'_, Rule>> {
+    let p = HoiParser::parse(rule, s).ok()?.next()?;
+    if p.as_str().len() != s.len() { return None; }
+    p.into_inner().next()
+}
+
+// Try to detect a date-like pattern from string content; out-of-range dates stay strings
+pub(crate) fn try_parse_date_like(s: &str) -> Option<Date> {
+    let parts: Vec<&str> = s.split('.').collect();
+    if !(3..=6).contains(&parts.len()) { return None; }
+    fn all_digits(x: &str) -> bool { x.chars().all(|c| c.is_ascii_digit()) }
+    if !(all_digits(parts[0]) && (3..=4).contains(&parts[0].len())) { return None; }
+    for part in &parts[1..] {
+        if !all_digits(part) || !(1..=2).contains(&part.len()) { return None; }
+    }
+    parse_date_str(s).ok()
+}
+
+// State threaded through the tree walk. In lenient mode a key directly followed by a block
+// (`key { ... }`) is read as `key = { ... }` and the key's span recorded; otherwise it is an error.
+// In strict mode a list mixing pairs and bare values is an error instead of becoming a Block.
+#[derive(Default)]
+pub(crate) struct ParseState { pub(crate) lenient: bool, pub(crate) strict: bool, pub(crate) missing_operators: Vec<Span> }
+
+// Parse color: color space keyword followed by a block of numbers
+fn parse_color(p: Pair<Rule>) -> Result<Value, ParseError> {
+    let mut inner = p.into_inner();
+    let space = parse_color_space(inner.next().unwrap().as_str());
+    let components = inner.map(|n| parse_number_literal(&n)).collect::<Result<_, _>>()?;
+    Ok(Value::Color { space, components })
+}
+
+pub(crate) fn parse_color_space(s: &str) -> ColorSpace {
+    match s {
+        "hsv" => ColorSpace::Hsv,
+        "hsv360" => ColorSpace::Hsv360,
+        _ => ColorSpace::Rgb,
+    }
+}
+
+// Error for a color keyword followed by a block that is not a color, located at the keyword
+pub(crate) fn invalid_color(p: &Pair<Rule>) -> ParseError {
+    let (line, col) = p.line_col();
+    ParseError::InvalidColor { line, col }
+}
+
+// Parse value: unwrap the value wrapper node into a concrete type
+fn parse_value(p: Pair<Rule>, st: &mut ParseState) -> Result<Value, ParseError> {
+    Ok(match p.as_rule() {
+        Rule::value => parse_value(p.into_inner().next().unwrap(), st)?,
+        Rule::block => parse_block(p, st)?,
+        Rule::color => parse_color(p)?,
+        Rule::bad_color => return Err(invalid_color(&p)),
+        Rule::string | Rule::date | Rule::number | Rule::boolean | Rule::identifier => Value::Atom(parse_atom(p)?),
+        _ => Value::Atom(Atom::Ident(p.as_str().to_string())),
+    })
+}
+
+// Parse block, classified as an Array or a Block by Value::from_items
+fn parse_block(p: Pair<Rule>, st: &mut ParseState) -> Result<Value, ParseError> {
+    let mut items: Vec<Item> = Vec::new();
+    for child in p.into_inner() {
+        if child.as_rule() == Rule::body { items = parse_body(child, st, false)?; }
+    }
+    Ok(Value::from_items(items))
+}
+
+// Parse the items of a body, attaching each comment that starts on the line where the previous item ends
+fn parse_body(body: Pair<Rule>, st: &mut ParseState, top_level: bool) -> Result<Vec<Item>, ParseError> {
+    let input = body.as_span().get_input();
+    // The body's child count is known up front; reserve once instead of growing
+    let children = body.into_inner();
+    let mut items = Vec::with_capacity(children.len());
+    let mut prev_end = None;
+    let mut last_end = None;
+    let mut has_pair = false;
+    let mut first_value = None;
+    for it in children {
+        let span = it.as_span();
+        let item = parse_item(it, st)?;
+        match item {
+            Item::Pair { .. } => has_pair = true,
+            Item::Value { .. } if first_value.is_none() => first_value = Some(span),
+            _ => {}
+        }
+        if let (true, true, Some(value)) = (st.strict, has_pair, first_value) {
+            let (line, col) = value.start_pos().line_col();
+            return Err(ParseError::MixedBlock { value: value.as_str().to_string(), line, col });
+        }
+        if let (Item::Comment(text), Some(end)) = (&item, prev_end) {
+            if !input[end..span.start()].contains('\n') && attach_trailing_comment(items.last_mut(), text) {
+                prev_end = None;
+                last_end = Some(span.end());
+                continue;
+            }
+        }
+        if let (true, Some(end)) = (top_level, last_end) {
+            if let Some(blank) = blank_lines(&input[end..span.start()]) { items.push(blank); }
+        }
+        prev_end = Some(span.end());
+        last_end = prev_end;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// Blank lines in the whitespace between two items, if there are any
+pub(crate) fn blank_lines(gap: &str) -> Option<Item> {
+    let lines = gap.matches('\n').count().saturating_sub(1);
+    if lines == 0 { None } else { Some(Item::Blank(lines.min(u8::MAX as usize) as u8)) }
+}
+
+// Attach `text` as the trailing comment of a pair or value; false if `item` cannot take one
+pub(crate) fn attach_trailing_comment(item: Option<&mut Item>, text: &str) -> bool {
+    match item {
+        Some(Item::Pair { trailing_comment: slot @ None, .. } | Item::Value { trailing_comment: slot @ None, .. }) => {
+            *slot = Some(text.to_string());
+            true
+        }
+        _ => false,
+    }
+}
+
+// Parse item (pair or plain value)
+pub(crate) fn parse_item(p: Pair<Rule>, st: &mut ParseState) -> Result<Item, ParseError> {
+    Ok(match p.as_rule() {
+        // Item is a wrapper node; inside is either a pair or a value
+        Rule::item => {
+            let mut inner = p.into_inner();
+            if let Some(child) = inner.next() { return parse_item(child, st); }
+            Item::Value { value: Value::Atom(Atom::Ident(String::new())), trailing_comment: None }
+        }
+        Rule::pair => {
+            let mut it = p.into_inner();
+            let key = parse_key(it.next().unwrap())?;
+            let op = parse_operator(it.next().unwrap());
+            let value = parse_value(it.next().unwrap(), st)?;
+            Item::Pair { key, op, value, trailing_comment: None, leading_comments: Vec::new() }
+        }
+        Rule::bare_pair => {
+            let mut it = p.into_inner();
+            let key_pair = it.next().unwrap();
+            let span = key_pair.as_span();
+            if !st.lenient {
+                let (line, col) = key_pair.line_col();
+                return Err(ParseError::MissingOperator { key: key_pair.as_str().to_string(), line, col });
+            }
+            st.missing_operators.push(Span { start: span.start(), end: span.end() });
+            let key = parse_key(key_pair)?;
+            let value = parse_block(it.next().unwrap(), st)?;
+            Item::Pair { key, op: Operator::Eq, value, trailing_comment: None, leading_comments: Vec::new() }
+        }
+        Rule::value => Item::Value { value: parse_value(p, st)?, trailing_comment: None },
+        Rule::comment => Item::Comment(p.as_str().to_string()),
+        _ => Item::Value { value: Value::Atom(Atom::Ident(p.as_str().to_string())), trailing_comment: None },
+    })
+}
+
+// Parse the file rule into a list of items
+fn parse_root(pairs: Pairs<Rule>, st: &mut ParseState) -> Result<Vec<Item>, ParseError> {
+    let mut items = Vec::new();
+    let file = pairs.into_iter().next().unwrap();
+    for child in file.into_inner() {
+        if child.as_rule() == Rule::body { items = parse_body(child, st, true)?; }
+    }
+    Ok(items)
+}
+
+// Format date, as written in the source when the literal was kept.
+// Otherwise the date parts and the hour are plain numbers, and minutes and seconds have two digits like a clock.
+fn fmt_date(d: &Date) -> String {
+    if let Some(literal) = &d.literal { return literal.clone(); }
+    let mut out = format!("{}.{}.{}", d.y, d.m, d.d);
+    if let Some(h) = d.h { out.push_str(&format!(".{}", h)); }
+    if let Some(min) = d.min { out.push_str(&format!(".{:02}", min)); }
+    if let Some(sec) = d.sec { out.push_str(&format!(".{:02}", sec)); }
+    out
+}
+
+// Format number, as written in the source when the literal was kept
+fn fmt_number(n: &Number) -> String {
+    n.literal.clone().unwrap_or_else(|| fmt_f64(n.value))
+}
+
+// Plain decimal text of a number: whole values have no fraction or exponent (`1000000`),
+// and negative zero is written `0`
+fn fmt_f64(v: f64) -> String {
+    if v == 0.0 { "0".to_string() } else { v.to_string() }
+}
+
+fn fmt_bool(b: bool) -> &'static str {
+    if b { "yes" } else { "no" }
+}
+
+// Serialize atomic value
+fn serialize_atom(a: &Atom) -> String {
+    match a {
+        Atom::String(s) => format!("\"{}\"", escape_string(s)),
+        Atom::Ident(s) => s.clone(),
+        Atom::Number(n) => fmt_number(n),
+        Atom::Date(d) if d.quoted => format!("\"{}\"", fmt_date(d)),
+        Atom::Date(d) => fmt_date(d),
+        Atom::Bool(b) => b.literal.clone().unwrap_or_else(|| fmt_bool(b.value).to_string()),
+    }
+}
+
+// Serialize key
+fn serialize_key(k: &KeyAtom, opts: &SerializeOptions) -> String {
+    match k {
+        KeyAtom::Ident(s) => s.clone(),
+        KeyAtom::Number(n) => fmt_number(n),
+        KeyAtom::Date(d) => {
+            let quoted = match opts.date_key_quoting { DateQuoting::Preserve => d.quoted, DateQuoting::Always => true, DateQuoting::Never => false };
+            if quoted { format!("\"{}\"", fmt_date(d)) } else { fmt_date(d) }
+        }
+        KeyAtom::Quoted(s) => format!("\"{}\"", escape_string(s)),
+    }
+}
+
+// Key text as it would be written with default options
+pub(crate) fn key_text(k: &KeyAtom) -> String {
+    serialize_key(k, &SerializeOptions::default())
+}
+
+// Key text without quotes or escapes, as key paths and key lists name it: `"my key"` is `my key`, `"1939.9.1"` is `1939.9.1`
+pub(crate) fn key_name(k: &KeyAtom) -> Cow<'_, str> {
+    match k {
+        KeyAtom::Ident(s) | KeyAtom::Quoted(s) => Cow::Borrowed(s),
+        KeyAtom::Number(n) => Cow::Owned(fmt_number(n)),
+        KeyAtom::Date(d) => Cow::Owned(fmt_date(d)),
+    }
+}
+
+// Leading whitespace for the given nesting depth
+fn pad(depth: usize, opts: &SerializeOptions) -> String {
+    match opts.indent { Indent::Tabs => "\t".repeat(depth), Indent::Spaces(n) => " ".repeat(depth * n) }
+}
+
+// Serialize value (`indent` is the nesting depth)
+fn serialize_value(v: &Value, indent: usize, opts: &SerializeOptions) -> String {
+    // Empty containers stay on one line, whether read as an array or a block
+    if v.is_empty() { return "{}\n".to_string(); }
+    if let (Value::Array(_) | Value::Block(_), Some(limit)) = (v, opts.inline_blocks_under) {
+        if let Some(mut out) = inline_value(v, opts, limit) { out.push('\n'); return out; }
+    }
+    match v {
+        Value::Atom(a) => serialize_atom(a),
+        Value::Array(arr) => {
+            // Soft-wrap array at array_wrap_width characters (a single longer element uses its own line)
+            let rendered: Vec<String> = arr.iter().map(serialize_atom).collect();
+            let mut out = String::new();
+            out.push_str("{\n");
+            let mut line = String::new();
+            for (idx, elem) in rendered.iter().enumerate() {
+                let sep = if line.is_empty() { "" } else { " " };
+                let prospective_len = line.len() + sep.len() + elem.len();
+                if !line.is_empty() && prospective_len > opts.array_wrap_width {
+                    out.push_str(&pad(indent + 1, opts));
+                    out.push_str(&line);
+                    out.push('\n');
+                    line.clear();
+                }
+                if line.is_empty() {
+                    line.push_str(elem);
+                } else {
+                    line.push(' ');
+                    line.push_str(elem);
+                }
+                // If it is the last element, output the current line
+                if idx == rendered.len() - 1 {
+                    out.push_str(&pad(indent + 1, opts));
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+            out.push_str(&pad(indent, opts));
+            out.push_str("}\n");
+            out
+        }
+        Value::Color { space, components } => {
+            // Colors always stay on one line: rgb { 255 128 0 }
+            let name = match space { ColorSpace::Rgb => "rgb", ColorSpace::Hsv => "hsv", ColorSpace::Hsv360 => "hsv360" };
+            if components.is_empty() { return format!("{} {{}}", name); }
+            let rendered: Vec<String> = components.iter().map(fmt_number).collect();
+            format!("{} {{ {} }}", name, rendered.join(" "))
+        }
+        Value::Block(items) => {
+            let mut out = String::new();
+            out.push_str("{\n");
+            serialize_items(&mut out, items, indent + 1, opts);
+            out.push_str(&pad(indent, opts));
+            out.push_str("}\n");
+            out
+        }
+    }
+}
+
+// Single-line rendering of a value, or None if it contains a comment or reaches `limit` characters
+// Nested values get only the room left, so a deep block gives up without walking its whole depth
+fn inline_value(v: &Value, opts: &SerializeOptions, limit: usize) -> Option<String> {
+    if limit == 0 { return None; }
+    let out = match v {
+        _ if v.is_empty() => "{}".to_string(),
+        Value::Atom(_) | Value::Color { .. } => serialize_value(v, 0, opts),
+        Value::Array(arr) => {
+            let mut out = String::from("{");
+            for a in arr {
+                out.push(' ');
+                out.push_str(&serialize_atom(a));
+                if out.len() >= limit { return None; }
+            }
+            out.push_str(" }");
+            out
+        }
+        Value::Block(items) => {
+            let mut out = String::from("{");
+            for it in ordered(items, opts) {
+                out.push(' ');
+                match it {
+                    Item::Pair { trailing_comment: Some(_), .. } | Item::Value { trailing_comment: Some(_), .. } => return None,
+                    Item::Pair { leading_comments, .. } if !leading_comments.is_empty() => return None,
+                    Item::Pair { key, op, value, .. } => {
+                        out.push_str(&serialize_key(key, opts));
+                        out.push(' ');
+                        out.push_str(operator_text(op, opts));
+                        out.push(' ');
+                        out.push_str(&inline_value(value, opts, limit.saturating_sub(out.len()))?);
+                    }
+                    Item::Value { value, .. } => out.push_str(&inline_value(value, opts, limit.saturating_sub(out.len()))?),
+                    Item::Comment(_) | Item::Blank(_) => return None,
+                }
+                if out.len() >= limit { return None; }
+            }
+            out.push_str(" }");
+            out
+        }
+    };
+    if out.len() < limit { Some(out) } else { None }
+}
+
+// Operator text, honoring operator_overrides
+fn operator_text<'a>(op: &Operator, opts: &'a SerializeOptions) -> &'a str {
+    match opts.operator_overrides.iter().find(|(o, _)| o == op) {
+        Some((_, text)) => text,
+        None => operator_str(op),
+    }
+}
+
+// Standard operator text
+fn operator_str(op: &Operator) -> &'static str {
+    match op { Operator::Eq => "=", Operator::Ne => "!=", Operator::OptEq => "?=", Operator::Le => "<=", Operator::Ge => ">=", Operator::Lt => "<", Operator::Gt => ">" }
+}
+
+// Normalize comment spacing: keep the `#`/`##` marker, then exactly one space before the text
+fn normalize_comment(s: &str) -> String {
+    let text = s.trim_start_matches('#');
+    let marker = &s[..s.len() - text.len()];
+    let text = text.trim();
+    if text.is_empty() { marker.to_string() } else { format!("{} {}", marker, text) }
+}
+
+// Whether a pair's operator takes part in alignment
+fn is_aligned(value: &Value) -> bool {
+    matches!(value, Value::Atom(_) | Value::Color { .. })
+}
+
+// Append the serialized items of one block (or the file) to `out`, aligning operators when enabled.
+// Nested blocks are walked with an explicit stack, so deep nesting cannot overflow the call stack.
+fn serialize_items(out: &mut String, items: &[Item], indent: usize, opts: &SerializeOptions) {
+    // Open blocks: remaining items, depth, key width, and the trailing comment of the closing brace
+    // (None for the outermost list, which has no brace of its own)
+    let mut stack = vec![(ordered(items, opts).into_iter(), indent, key_width(items, opts), None)];
+    while let Some((rest, depth, width, _)) = stack.last_mut() {
+        let (depth, width) = (*depth, *width);
+        let Some(it) = rest.next() else {
+            if let Some((_, _, _, Some(closing))) = stack.pop() {
+                out.push_str(&pad(depth - 1, opts));
+                out.push('}');
+                end_line(out, closing, opts);
+            }
+            continue;
+        };
+        let (inner, trailing) = match it {
+            Item::Pair { key, op, value, trailing_comment, leading_comments } => match expanded_block(value, opts) {
+                Some(inner) => {
+                    for c in leading_comments { push_comment_line(out, c, depth, opts); }
+                    out.push_str(&pad(depth, opts));
+                    out.push_str(&serialize_key(key, opts));
+                    out.push(' ');
+                    out.push_str(operator_text(op, opts));
+                    out.push(' ');
+                    (inner, trailing_comment)
+                }
+                None => { out.push_str(&serialize_item(it, depth, width, opts)); continue; }
+            },
+            Item::Value { value, trailing_comment } => match expanded_block(value, opts) {
+                Some(inner) => { out.push_str(&pad(depth, opts)); (inner, trailing_comment) }
+                None => { out.push_str(&serialize_item(it, depth, width, opts)); continue; }
+            },
+            _ => { out.push_str(&serialize_item(it, depth, width, opts)); continue; }
+        };
+        out.push_str("{\n");
+        stack.push((ordered(inner, opts).into_iter(), depth + 1, key_width(inner, opts), Some(trailing.as_deref())));
+    }
+}
+
+// Width scalar pair keys are padded to when align_operators is set
+fn key_width(items: &[Item], opts: &SerializeOptions) -> usize {
+    if !opts.align_operators { return 0; }
+    items.iter()
+        .filter_map(|it| match it { Item::Pair { key, value, .. } if is_aligned(value) => Some(serialize_key(key, opts).chars().count()), _ => None })
+        .max()
+        .unwrap_or(0)
+}
+
+// Items of a block written across several lines, or None when the value fits on one line
+fn expanded_block<'a>(v: &'a Value, opts: &SerializeOptions) -> Option<&'a [Item]> {
+    let Value::Block(items) = v else { return None };
+    if items.is_empty() { return None; }
+    match opts.inline_blocks_under {
+        Some(limit) if inline_value(v, opts, limit).is_some() => None,
+        _ => Some(items),
+    }
+}
+
+// Items in output order, applying sort_keys
+fn ordered<'a>(items: &'a [Item], opts: &SerializeOptions) -> Vec<&'a Item> {
+    let mut order: Vec<&Item> = items.iter().collect();
+    if opts.sort_keys {
+        let mut pairs: Vec<(String, &Item)> = items.iter()
+            .filter_map(|it| match it { Item::Pair { key, .. } => Some((serialize_key(key, opts), it)), _ => None })
+            .collect();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut sorted = pairs.into_iter().map(|(_, it)| it);
+        for slot in order.iter_mut().filter(|it| matches!(it, Item::Pair { .. })) {
+            *slot = sorted.next().unwrap();
+        }
+    }
+    order
+}
+
+// Rough serialized size: the text of keys, atoms and comments plus indentation and punctuation,
+// erring on the large side so the output buffer is allocated once
+fn estimated_len(items: &[Item]) -> usize {
+    let mut total = 0;
+    let mut stack = vec![(items, 0)];
+    while let Some((items, depth)) = stack.pop() {
+        for it in items {
+            // Indentation, spaces around the operator, and the newline
+            total += depth * 2 + 6;
+            let value = match it {
+                Item::Pair { key, value, trailing_comment, leading_comments, .. } => {
+                    total += key_len(key) + trailing_comment.as_ref().map_or(0, |c| c.len() + 1);
+                    total += leading_comments.iter().map(|c| depth * 2 + c.len() + 1).sum::<usize>();
+                    value
+                }
+                Item::Value { value, trailing_comment } => {
+                    total += trailing_comment.as_ref().map_or(0, |c| c.len() + 1);
+                    value
+                }
+                Item::Comment(c) => { total += c.len(); continue; }
+                Item::Blank(n) => { total += *n as usize; continue; }
+            };
+            match value {
+                Value::Atom(a) => total += atom_len(a),
+                // Braces, their lines, and a separator per atom
+                Value::Array(arr) => total += depth * 4 + 8 + arr.iter().map(|a| atom_len(a) + 1).sum::<usize>(),
+                Value::Color { components, .. } => total += 12 + components.len() * 8,
+                Value::Block(inner) => {
+                    total += depth * 2 + 4;
+                    stack.push((inner, depth + 1));
+                }
+            }
+        }
+    }
+    total
+}
+
+fn key_len(k: &KeyAtom) -> usize {
+    match k { KeyAtom::Ident(s) | KeyAtom::Quoted(s) => s.len() + 2, KeyAtom::Number(n) => number_len(n), KeyAtom::Date(_) => 21 }
+}
+
+fn atom_len(a: &Atom) -> usize {
+    match a { Atom::String(s) | Atom::Ident(s) => s.len() + 2, Atom::Number(n) => number_len(n), Atom::Date(_) => 21, Atom::Bool(_) => 3 }
+}
+
+fn number_len(n: &Number) -> usize {
+    n.literal.as_ref().map_or(12, String::len)
+}
+
+// Serialize item (`indent` is the nesting depth; scalar pair keys are padded to `key_width`)
+fn serialize_item(i: &Item, indent: usize, key_width: usize, opts: &SerializeOptions) -> String {
+    match i {
+        Item::Pair { key, op, value, trailing_comment, leading_comments } => {
+            let mut line = String::new();
+            for c in leading_comments { push_comment_line(&mut line, c, indent, opts); }
+            line.push_str(&pad(indent, opts));
+            let key = serialize_key(key, opts);
+            line.push_str(&key);
+            if is_aligned(value) {
+                for _ in key.chars().count()..key_width { line.push(' '); }
+            }
+            line.push(' ');
+            line.push_str(operator_text(op, opts));
+            line.push(' ');
+            line.push_str(&serialize_value(value, indent, opts));
+            end_line(&mut line, trailing_comment.as_deref(), opts);
+            line
+        }
+        Item::Value { value, trailing_comment } => {
+            let mut line = String::new();
+            line.push_str(&pad(indent, opts));
+            line.push_str(&serialize_value(value, indent, opts));
+            end_line(&mut line, trailing_comment.as_deref(), opts);
+            line
+        }
+        Item::Comment(s) => {
+            let mut line = String::new();
+            push_comment_line(&mut line, s, indent, opts);
+            line
+        }
+        Item::Blank(n) => "\n".repeat(*n as usize),
+    }
+}
+
+// Append a comment on a line of its own
+fn push_comment_line(out: &mut String, s: &str, indent: usize, opts: &SerializeOptions) {
+    out.push_str(&pad(indent, opts));
+    if opts.normalize_comments { out.push_str(&normalize_comment(s)); } else { out.push_str(s); }
+    out.push('\n');
+}
+
+// Finish the line of a pair or value: blocks and arrays already end in a newline,
+// which goes after the trailing comment when there is one
+fn end_line(line: &mut String, trailing_comment: Option<&str>, opts: &SerializeOptions) {
+    if line.ends_with('\n') { line.pop(); }
+    if let Some(c) = trailing_comment {
+        line.push(' ');
+        if opts.normalize_comments { line.push_str(&normalize_comment(c)); } else { line.push_str(c); }
+    }
+    line.push('\n');
+}
+
+// Parse script text into a list of items. A leading UTF-8 BOM is skipped.
+// When the grammar rejects the input, a brace imbalance is reported in preference to the raw pest error.
+pub fn parse_str(input: &str) -> Result<Vec<Item>, ParseError> {
+    parse_str_with(input, &ParseOptions::default())
+}
+
+// Like parse_str, with the limits in `opts`
+pub fn parse_str_with(input: &str, opts: &ParseOptions) -> Result<Vec<Item>, ParseError> {
+    parse_root(parse_raw_with(input, opts)?, &mut ParseState::default())
+}
+
+// Like parse_str, but a block (or the file) holding both pairs and bare values is an error
+// rather than a Block, for validators that expect every list to be all pairs or all values
+pub fn parse_str_strict(input: &str) -> Result<Vec<Item>, ParseError> {
+    parse_root(parse_raw(input)?, &mut ParseState { strict: true, ..ParseState::default() })
+}
+
+// The raw pest parse of `input` (Rule::file at the root), for tools walking the grammar tree themselves.
+// Errors and BOM handling match parse_str, but none of the AST checks run (dates, numbers, missing operators);
+// prefer parse_str unless the token tree itself is needed.
+pub fn parse_raw(input: &str) -> Result<Pairs<'_, Rule>, ParseError> {
+    parse_raw_with(input, &ParseOptions::default())
+}
+
+// The grammar recurses once per nesting level, so the depth is checked before handing it the input
+fn parse_raw_with<'a>(input: &'a str, opts: &ParseOptions) -> Result<Pairs<'a, Rule>, ParseError> {
+    if let Some((_, at)) = deep_braces(input, opts.max_depth).next() { return Err(too_deep(at, opts.max_depth)); }
+    let input = input.strip_prefix(BOM).unwrap_or(input);
+    HoiParser::parse(Rule::file, input).map_err(|e| check_braces(input).unwrap_or_else(|| e.into()))
+}
+
+// Read and parse a script file
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Vec<Item>, ParseError> {
+    parse_str(&fs::read_to_string(path)?)
+}
+
+// Serialize items with default options and write them to a file, ending with a newline
+pub fn write_file(path: impl AsRef<Path>, items: &[Item]) -> Result<(), ParseError> {
+    let mut out = serialize_ast(items);
+    if !out.ends_with('\n') { out.push('\n'); }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+// Serialize items with default options
+pub fn serialize_ast(items: &[Item]) -> String {
+    serialize_ast_with(items, &SerializeOptions::default())
+}
+
+// Serialize items with the given options
+pub fn serialize_ast_with(items: &[Item], opts: &SerializeOptions) -> String {
+    let mut out = String::with_capacity(estimated_len(items));
+    serialize_items(&mut out, items, 0, opts);
+    // Trailing blank lines would leave a double newline
+    out.truncate(out.trim_end_matches('\n').len());
+    if opts.final_newline && !out.is_empty() { out.push('\n'); }
+    if opts.line_ending == LineEnding::Crlf { out = out.replace('\n', "\r\n"); }
+    out
+}
+
+// Render a single value as serialize_ast writes it at the top level, without the final newline
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let out = serialize_value(self, 0, &SerializeOptions::default());
+        f.write_str(out.strip_suffix('\n').unwrap_or(&out))
+    }
+}
+
+// Render a single item as serialize_ast writes it at the top level, without the final newline
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let out = serialize_item(self, 0, 0, &SerializeOptions::default());
+        f.write_str(out.strip_suffix('\n').unwrap_or(&out))
+    }
+}
+
+// A whole script's items, so a file can be read with `text.parse::<Script>()?` and written with `to_string()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script(pub Vec<Item>);
+
+impl FromStr for Script {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, ParseError> { parse_str(s).map(Script) }
+}
+
+// Same text as serialize_ast
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&serialize_ast(&self.0)) }
+}
+
+impl From<Vec<Item>> for Script {
+    fn from(items: Vec<Item>) -> Self { Script(items) }
+}
+
+impl From<Script> for Vec<Item> {
+    fn from(script: Script) -> Self { script.0 }
+}
+
+// Iterating a script walks its top-level items, so `for item in &script` works without reaching into the field
+impl IntoIterator for Script {
+    type Item = Item;
+    type IntoIter = std::vec::IntoIter<Item>;
+    fn into_iter(self) -> Self::IntoIter { self.0.into_iter() }
+}
+
+impl<'a> IntoIterator for &'a Script {
+    type Item = &'a Item;
+    type IntoIter = std::slice::Iter<'a, Item>;
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+impl<'a> IntoIterator for &'a mut Script {
+    type Item = &'a mut Item;
+    type IntoIter = std::slice::IterMut<'a, Item>;
+    fn into_iter(self) -> Self::IntoIter { self.0.iter_mut() }
+}
+
+impl FromIterator<Item> for Script {
+    fn from_iter<I: IntoIterator<Item = Item>>(iter: I) -> Self { Script(iter.into_iter().collect()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Serialize `input`, checking that the output parses back to the same items
+    // and that serializing those again gives the same text
+    fn round_trip(input: &str) -> String {
+        let items = parse_str(input).unwrap();
+        let out = serialize_ast(&items);
+        let reparsed = parse_str(&out).unwrap();
+        assert_eq!(reparsed, items, "output:\n{}", out);
+        assert_eq!(serialize_ast(&reparsed), out);
+        out
+    }
+
+    // Value of the only pair in `items`
+    fn only_value(items: &[Item]) -> &Value {
+        match items {
+            [Item::Pair { value, .. }] => value,
+            _ => panic!("expected a single pair, got {:?}", items),
+        }
+    }
+
+    #[test]
+    fn round_trip_empty_block() {
+        assert_eq!(round_trip("a = {}"), "a = {}\n");
+        assert_eq!(round_trip("a = {\n\n}"), "a = {}\n");
+    }
+
+    #[test]
+    fn round_trip_value_only_block() {
+        assert_eq!(round_trip("a = { 1 b \"c d\" }"), "a = {\n  1 b \"c d\"\n}\n");
+        assert!(matches!(only_value(&parse_str("a = { 1 b }").unwrap()), Value::Array(_)));
+    }
+
+    #[test]
+    fn round_trip_pair_only_block() {
+        assert_eq!(round_trip("a = { b = 1 c = yes }"), "a = {\n  b = 1\n  c = yes\n}\n");
+    }
+
+    #[test]
+    fn round_trip_mixed_block_keeps_order() {
+        let out = round_trip("a = { x b = 1 y }");
+        assert_eq!(out, "a = {\n  x\n  b = 1\n  y\n}\n");
+        assert!(matches!(only_value(&parse_str(&out).unwrap()), Value::Block(items) if items.len() == 3));
+    }
+
+    #[test]
+    fn round_trip_array_in_block() {
+        assert_eq!(round_trip("a = { b = { 1 2 } c = 3 }"), "a = {\n  b = {\n    1 2\n  }\n  c = 3\n}\n");
+    }
+
+    #[test]
+    fn round_trip_blocks_in_list() {
+        let out = round_trip("a = { { b = 1 } { 1 2 } }");
+        assert_eq!(out, "a = {\n  {\n    b = 1\n  }\n  {\n    1 2\n  }\n}\n");
+    }
+
+    #[test]
+    fn round_trip_commented_blocks() {
+        assert_eq!(round_trip("a = {\n# head\nb = 1 # tail\n}"), "a = {\n  # head\n  b = 1 # tail\n}\n");
+        // A comment turns a value list into a block
+        let out = round_trip("a = {\n1 2 # two\n3\n}");
+        assert_eq!(out, "a = {\n  1\n  2 # two\n  3\n}\n");
+        assert!(matches!(only_value(&parse_str(&out).unwrap()), Value::Block(_)));
+    }
+
+    #[test]
+    fn round_trip_single_element_blocks() {
+        assert_eq!(round_trip("a = { 1 }"), "a = {\n  1\n}\n");
+        assert_eq!(round_trip("a = { b = 1 }"), "a = {\n  b = 1\n}\n");
+    }
+
+    #[test]
+    fn round_trip_long_array_wraps() {
+        let numbers: Vec<String> = (1000..1100).map(|n| n.to_string()).collect();
+        let out = round_trip(&format!("a = {{ {} }}", numbers.join(" ")));
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines.len() > 3);
+        assert!(lines.iter().all(|l| l.trim().len() <= SerializeOptions::default().array_wrap_width));
+    }
+
+    #[test]
+    fn colors_with_three_and_four_components() {
+        let items = parse_str("a = rgb { 255 128 0 }\nb = hsv { 0.5 1 1 0.25 }").unwrap();
+        assert_eq!(items[0], Item::Pair {
+            key: KeyAtom::Ident("a".into()), op: Operator::Eq,
+            value: Value::Color { space: ColorSpace::Rgb, components: vec![255.0.into(), 128.0.into(), 0.0.into()] },
+            trailing_comment: None, leading_comments: Vec::new(),
+        });
+        assert!(matches!(&items[1], Item::Pair { value: Value::Color { space: ColorSpace::Hsv, components }, .. } if components.len() == 4));
+        assert_eq!(round_trip("a = rgb { 255 128 0 }\nb = hsv { 0.5 1 1 0.25 }"), "a = rgb { 255 128 0 }\nb = hsv { 0.5 1 1 0.25 }\n");
+    }
+
+    #[test]
+    fn colors_need_three_or_four_components() {
+        // Anything else after a color keyword is an error, not the keyword followed by a separate block
+        for input in ["a = rgb { 1 2 }", "c = rgb { 1 2 3 # note\n }", "c = hsv { 1 2 x }", "rgb { 1 2 }"] {
+            assert!(matches!(parse_str(input), Err(ParseError::InvalidColor { line: 1, .. })), "{}", input);
+            assert!(parse_str_borrowed(input).is_err(), "{}", input);
+        }
+        let err = parse_str("a = 1\nc = rgb { 1 2 3 # note\n }").unwrap_err();
+        assert_eq!(err.to_string(), "invalid color at line 2, col 5: expected 3 or 4 numbers and no comments");
+        // A key named like a color space is still a key
+        assert!(parse_str("rgb = { 1 2 }").is_ok());
+        let empty = Value::Color { space: ColorSpace::Rgb, components: Vec::new() };
+        assert_eq!(empty.to_string(), "rgb {}");
+    }
+
+    #[test]
+    fn color_components_keep_their_literal() {
+        assert_eq!(round_trip("c = hsv { 1.0 0.50 0.5 }\nd = rgb { +1 2E2 3 }"), "c = hsv { 1.0 0.50 0.5 }\nd = rgb { +1 2E2 3 }\n");
+        assert_eq!(parse_str("c = hsv { 1.0 0.50 0.5 }").unwrap(), parse_str("c = hsv { 1 0.5 0.5 }").unwrap());
+    }
+
+    #[test]
+    fn round_trip_history_file_with_date_keys() {
+        let input = "capital = 64\n1936.1.1 = {\n  set_politics = {\n    ruling_party = fascism\n  }\n}\n\"1939.9.1.12\" = {\n  declare_war_on = {\n    target = POL\n  }\n}\n";
+        assert_eq!(round_trip(input), input);
+        let items = parse_str(input).unwrap();
+        let quoting = |q| serialize_ast_with(&items, &SerializeOptions { date_key_quoting: q, ..Default::default() });
+        assert!(quoting(DateQuoting::Always).contains("\"1936.1.1\" = {"));
+        assert!(quoting(DateQuoting::Never).contains("\n1939.9.1.12 = {"));
+    }
+
+    // A list grown one push at a time ends with spare capacity (doubling from 4 reaches 8192 for 5000 elements);
+    // an exact capacity shows each list was allocated once
+    #[test]
+    fn large_lists_are_allocated_once() {
+        let atoms: Vec<String> = (0..5000).map(|n| n.to_string()).collect();
+        let pairs: Vec<String> = (0..5000).map(|n| format!("k{} = {}", n, n)).collect();
+        let items = parse_str(&format!("a = {{ {} }}\nb = {{ {} }}", atoms.join(" "), pairs.join(" "))).unwrap();
+        let (Item::Pair { value: Value::Array(array), .. }, Item::Pair { value: Value::Block(block), .. }) = (&items[0], &items[1]) else {
+            panic!("expected an array and a block");
+        };
+        assert_eq!((array.len(), array.capacity()), (5000, 5000));
+        assert_eq!((block.len(), block.capacity()), (5000, 5000));
+    }
+
+    #[test]
+    fn valid_date_parses() {
+        assert_eq!(parse_date_str("1936.2.28.23"), Ok(Date { y: 1936, m: 2, d: 28, h: Some(23), min: None, sec: None, quoted: false, literal: None }));
+    }
+
+    #[test]
+    fn out_of_range_month_is_an_error() {
+        assert_eq!(parse_date_str("1936.13.1"), Err("month out of range"));
+        let err = parse_str("a = 1\nstart = 1936.13.40").unwrap_err();
+        assert!(matches!(&err, ParseError::InvalidDate { reason: "month out of range", line: 2, col: 9, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn missing_date_component_is_an_error() {
+        assert_eq!(parse_date_str("1936..1"), Err("missing component"));
+        assert_eq!(parse_date_str("1936.1"), Err("missing component"));
+        // In a file the token is an identifier (the grammar's date needs all three parts), never a half-read date
+        assert_eq!(only_value(&parse_str("a = 1936..1").unwrap()), &Value::Atom(Atom::Ident("1936..1".into())));
+    }
+
+    #[test]
+    fn trailing_comments_stay_on_their_line() {
+        let input = "infantry_equipment = 10 # starting stock\nlist = {\n  1 2\n} # provinces\nvalue # bare\n";
+        assert_eq!(round_trip(input), input);
+        let items = parse_str(input).unwrap();
+        assert!(matches!(&items[0], Item::Pair { trailing_comment: Some(c), .. } if c == "# starting stock"));
+        assert!(matches!(&items[2], Item::Value { trailing_comment: Some(c), .. } if c == "# bare"));
+    }
+
+    #[test]
+    fn comment_markers_survive_unchanged() {
+        let input = "# single\n## double\n#no space\n###   wide\nideas = {\n  ## section\n  a = 1 ## note\n  #b = 2\n}\n";
+        assert_eq!(round_trip(input), input);
+        assert_eq!(format_str(input, &FormatOptions::default()).unwrap(), input);
+    }
+
+    #[test]
+    fn normalize_comments_keeps_the_marker() {
+        let items = parse_str("#a\n##  b\n").unwrap();
+        let out = serialize_ast_with(&items, &SerializeOptions { normalize_comments: true, ..Default::default() });
+        assert_eq!(out, "# a\n## b\n");
+    }
+
+    #[test]
+    fn quoted_key_with_space_round_trips() {
+        let input = "\"x y\" = 1\n\"say \\\"hi\\\"\" = yes\n";
+        assert_eq!(round_trip(input), input);
+        let items = parse_str(input).unwrap();
+        assert!(matches!(&items[0], Item::Pair { key: KeyAtom::Quoted(k), .. } if k == "x y"));
+        assert!(matches!(&items[1], Item::Pair { key: KeyAtom::Quoted(k), .. } if k == "say \"hi\""));
+    }
+
+    #[test]
+    fn signed_numbers_round_trip() {
+        let input = "attack = -0.5\ndefence = -10\nbonus = +3\n-5 = yes\n";
+        assert_eq!(round_trip(input), input);
+        let items = parse_str(input).unwrap();
+        let number = |i: usize| match &items[i] { Item::Pair { value: Value::Atom(Atom::Number(n)), .. } => n.value, it => panic!("{:?}", it) };
+        assert_eq!((number(0), number(1), number(2)), (-0.5, -10.0, 3.0));
+        assert!(matches!(&items[3], Item::Pair { key: KeyAtom::Number(n), .. } if n.value == -5.0));
+    }
+
+    #[test]
+    fn scientific_numbers_keep_their_literal() {
+        let input = "a = 1e3\nb = 1.5e-3\nc = 2E6\n";
+        assert_eq!(round_trip(input), input);
+        let items = parse_str(input).unwrap();
+        let values: Vec<f64> = items.iter().map(|it| match it { Item::Pair { value: Value::Atom(Atom::Number(n)), .. } => n.value, _ => f64::NAN }).collect();
+        assert_eq!(values, [1000.0, 0.0015, 2_000_000.0]);
+    }
+
+    #[test]
+    fn empty_block_round_trips_on_one_line() {
+        assert_eq!(round_trip("foo = {}\n"), "foo = {}\n");
+        let items = parse_str("foo = {}").unwrap();
+        assert!(only_value(&items).is_empty());
+        assert!(Value::Block(Vec::new()).is_empty());
+        assert!(!Value::Atom(Atom::Ident("x".into())).is_empty());
+        assert_eq!(serialize_ast(&[Item::Pair { key: KeyAtom::Ident("foo".into()), op: Operator::Eq, value: Value::Block(Vec::new()), trailing_comment: None, leading_comments: Vec::new() }]), "foo = {}\n");
+    }
+
+    #[test]
+    fn differently_formatted_inputs_are_equal() {
+        let compact = parse_str("focus={id=GER_1 cost=10 prerequisite={focus=GER_0} x=1.50 flag=YES}").unwrap();
+        let spread = parse_str("focus = {\n\tid = GER_1\n\tcost = 10.0\n\tprerequisite = {\n\t\tfocus = GER_0\n\t}\n\tx = 1.5\n\tflag = yes\n}\n").unwrap();
+        assert_eq!(compact, spread);
+        assert_ne!(compact, parse_str("focus = { id = GER_1 cost = 11 prerequisite = { focus = GER_0 } x = 1.5 flag = yes }").unwrap());
+        // Numbers compare by bits, so -0 and 0 differ
+        assert_ne!(parse_str("a = -0").unwrap(), parse_str("a = 0").unwrap());
+    }
+
+    #[test]
+    fn parse_file_and_write_file_round_trip_a_fixture() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/history.txt");
+        let items = parse_file(&fixture).unwrap();
+        let out = std::env::temp_dir().join(format!("clausewitz-write-file-{}.txt", std::process::id()));
+        write_file(&out, &items).unwrap();
+        let written = fs::read_to_string(&out).unwrap();
+        // A leading BOM is skipped when reading
+        fs::write(&out, format!("{}{}", BOM, written)).unwrap();
+        let reread = parse_file(&out).unwrap();
+        fs::remove_file(&out).unwrap();
+        assert!(written.ends_with("}\n"));
+        assert_eq!(reread, items);
+        assert!(matches!(parse_file(fixture.with_extension("missing")), Err(ParseError::Io(_))));
+    }
+
+    #[test]
+    fn sort_keys_orders_pairs_recursively() {
+        let opts = SerializeOptions { sort_keys: true, ..SerializeOptions::default() };
+        let items = parse_str("c = 1\n# keep\na = { z = 1 y = 2 }\nb = 3").unwrap();
+        let out = serialize_ast_with(&items, &opts);
+        assert_eq!(out, "a = {\n  y = 2\n  z = 1\n}\n# keep\nb = 3\nc = 1\n");
+        // Sorting again changes nothing
+        assert_eq!(serialize_ast_with(&parse_str(&out).unwrap(), &opts), out);
+    }
+
+    #[test]
+    fn blank_line_runs_round_trip() {
+        assert_eq!(round_trip("a = 1\n\nb = 2\n\n\n\nc = 3"), "a = 1\n\nb = 2\n\n\n\nc = 3\n");
+        // Only runs between top-level items are kept
+        assert_eq!(round_trip("a = {\n  b = 1\n\n  c = 2\n}"), "a = {\n  b = 1\n  c = 2\n}\n");
+        assert_eq!(parse_str("a = 1\n  \t\n\nb = 2").unwrap()[1], Item::Blank(2));
+        // Whitespace before the first and after the last item is not kept
+        assert_eq!(round_trip("\n\na = 1\n\n"), "a = 1\n");
+    }
+
+    #[test]
+    fn escaped_quotes_and_backslashes_round_trip() {
+        let input = r#"a = "say \"hi\" to C:\dir\\""#;
+        assert_eq!(round_trip(input), format!("{}\n", input));
+        assert_eq!(only_value(&parse_str(input).unwrap()), &Value::Atom(Atom::String(r#"say "hi" to C:\dir\"#.to_string())));
+        // Built text is escaped on the way out
+        let built = vec![Item::Pair { key: KeyAtom::Ident("b".into()), op: Operator::Eq, value: Value::Atom(Atom::String("x\"y\\".into())), trailing_comment: None, leading_comments: Vec::new() }];
+        assert_eq!(parse_str(&serialize_ast(&built)).unwrap(), built);
+    }
+
+    #[test]
+    fn booleans_keep_their_casing() {
+        assert_eq!(round_trip("a = YES\nb = No\nc = yEs\nd = yes"), "a = YES\nb = No\nc = yEs\nd = yes\n");
+        let items = parse_str("a = YES\nb = No\nc = yes").unwrap();
+        let values: Vec<bool> = items.iter().map(|it| match it {
+            Item::Pair { value: Value::Atom(Atom::Bool(b)), .. } => b.value,
+            other => panic!("not a boolean: {:?}", other),
+        }).collect();
+        assert_eq!(values, [true, false, true]);
+        // Casing is formatting, not content
+        assert_eq!(parse_str("a = YES").unwrap(), parse_str("a = yes").unwrap());
+    }
+
+    #[test]
+    fn date_parts_keep_the_width_they_were_read_with() {
+        assert_eq!(round_trip("a = 1936.1.1.12.30.00"), "a = 1936.1.1.12.30.00\n");
+        assert_eq!(round_trip("a = 1936.1.1.12.5\nb = 1936.01.01.09\nc = \"1936.1.1.1.05\""), "a = 1936.1.1.12.5\nb = 1936.01.01.09\nc = \"1936.1.1.1.05\"\n");
+        let Value::Atom(Atom::Date(d)) = only_value(&parse_str("a = 1936.1.1.12.30.00").unwrap()).clone() else { panic!() };
+        assert_eq!((d.h, d.min, d.sec), (Some(12), Some(30), Some(0)));
+        // Dates without a literal write minutes and seconds with two digits
+        let built = Date { literal: None, ..d };
+        assert_eq!(fmt_date(&Date { sec: Some(5), ..built }), "1936.1.1.12.30.05");
+        assert_eq!(parse_str("a = 1936.01.01").unwrap(), parse_str("a = 1936.1.1").unwrap());
+    }
+
+    #[test]
+    fn whole_numbers_print_as_integers() {
+        assert_eq!(round_trip("a = 1000000\n123456789012 = 5"), "a = 1000000\n123456789012 = 5\n");
+        assert!(matches!(&parse_str("123456789012 = 5").unwrap()[0], Item::Pair { key: KeyAtom::Number(n), .. } if n.value == 123456789012.0));
+        // Built numbers have no literal: no exponent, no `.0`, and negative zero is `0`
+        let num = |v: f64| Atom::Number(Number::from(v));
+        let built = vec![
+            Item::Pair { key: KeyAtom::Number(Number::from(1e15)), op: Operator::Eq, value: Value::Atom(num(-0.0)), trailing_comment: None, leading_comments: Vec::new() },
+            Item::Pair { key: KeyAtom::Ident("b".into()), op: Operator::Eq, value: Value::Array(vec![num(1e6), num(2.0), num(-3.0)]), trailing_comment: None, leading_comments: Vec::new() },
+        ];
+        assert_eq!(serialize_ast(&built), "1000000000000000 = 0\nb = {\n  1000000 2 -3\n}\n");
+    }
+
+    #[test]
+    fn scripts_and_values_iterate_and_collect() {
+        let mut script: Script = "a = 1\nb = { c = 2 }\nd = 3".parse().unwrap();
+        let keys: Vec<String> = (&script).into_iter().filter_map(|it| match it { Item::Pair { key, .. } => Some(key_text(key)), _ => None }).collect();
+        assert_eq!(keys, ["a", "b", "d"]);
+        for item in &mut script {
+            if let Item::Pair { trailing_comment, .. } = item { *trailing_comment = Some("# seen".into()); }
+        }
+        let kept: Script = script.into_iter().filter(|it| !matches!(it, Item::Pair { value: Value::Block(_), .. })).collect();
+        assert_eq!(kept.to_string(), "a = 1 # seen\nd = 3 # seen\n");
+        // Collecting into a Value classifies like the parser: atoms make an array, pairs a block
+        let array: Value = ["1", "2"].into_iter().map(Atom::from).collect();
+        assert_eq!(array, *only_value(&parse_str("x = { 1 2 }").unwrap()));
+        let Value::Block(inner) = only_value(&parse_str("x = { c = 2 }").unwrap()).clone() else { panic!() };
+        assert_eq!(inner.into_iter().collect::<Value>(), *only_value(&parse_str("x = { c = 2 }").unwrap()));
+    }
+
+    #[test]
+    fn crlf_files_round_trip_to_crlf() {
+        let input = "# header\r\nidea = {\r\n  cost = 150 # base\r\n}\r\n\r\nb = \"x\"\r\n";
+        let opts = SerializeOptions { line_ending: LineEnding::detect(input), ..SerializeOptions::default() };
+        assert_eq!(opts.line_ending, LineEnding::Crlf);
+        let items = parse_str(input).unwrap();
+        assert_eq!(serialize_ast_with(&items, &opts), input);
+        assert_eq!(items, parse_str(&input.replace("\r\n", "\n")).unwrap());
+        assert_eq!(LineEnding::detect("a = 1\nb = 2\r\nc = 3\n"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn parse_raw_exposes_the_token_tree() {
+        let pairs = parse_raw("\u{feff}a = 1\nb = { c = 2 d = { e = 3 } }\nf\n# note").unwrap();
+        let count = pairs.flatten().filter(|p| p.as_rule() == Rule::pair).count();
+        assert_eq!(count, 5);
+        let root = parse_raw("a = 1").unwrap().next().unwrap();
+        assert_eq!(root.as_rule(), Rule::file);
+        assert!(parse_raw("a = {").is_err());
+    }
+
+    #[test]
+    fn strict_mode_rejects_mixed_containers() {
+        let input = "provinces = {\n  1 2\n  owner = GER\n}\n";
+        let lenient = parse_str(input).unwrap();
+        assert!(matches!(only_value(&lenient), Value::Block(inner) if inner.len() == 3));
+        let err = parse_str_strict(input).unwrap_err();
+        assert!(matches!(&err, ParseError::MixedBlock { line: 2, col: 3, .. }), "{:?}", err);
+        // Plain arrays and pair-only blocks are fine either way
+        assert_eq!(parse_str_strict("a = { 1 2 }\nb = { c = 1 }").unwrap(), parse_str("a = { 1 2 }\nb = { c = 1 }").unwrap());
+    }
+
+    #[test]
+    fn serializes_5000_levels_without_recursing() {
+        const DEPTH: usize = 5000;
+        let pair = |value| Item::Pair { key: KeyAtom::Ident("a".into()), op: Operator::Eq, value, trailing_comment: None, leading_comments: Vec::new() };
+        let mut items = vec![pair(Value::Atom(Atom::Number(Number::from(1.0))))];
+        for _ in 0..DEPTH { items = vec![pair(Value::Block(items))]; }
+        let out = serialize_ast_with(&items, &SerializeOptions { indent: Indent::Tabs, ..SerializeOptions::default() });
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2 * DEPTH + 1);
+        assert_eq!(lines[DEPTH], format!("{}a = 1", "\t".repeat(DEPTH)));
+        assert_eq!(lines[DEPTH + 1], format!("{}}}", "\t".repeat(DEPTH - 1)));
+    }
+
+    // `depth` blocks nested inside each other
+    fn nested(depth: usize) -> String {
+        format!("{}x = 1{}", "a = { ".repeat(depth), " }".repeat(depth))
+    }
+
+    #[test]
+    fn nesting_up_to_max_depth_parses() {
+        let max = ParseOptions::default().max_depth;
+        let input = nested(max);
+        let items = parse_str(&input).unwrap();
+        assert_eq!(parse_str(&serialize_ast(&items)).unwrap(), items);
+        assert!(parse_str_recover(&input).1.is_empty());
+        assert!(parse_items(&input).all(|r| r.is_ok()));
+        let err = parse_str(&nested(max + 1)).unwrap_err();
+        assert!(matches!(err, ParseError::TooDeep { depth, span } if depth == max + 1 && span.start == 6 * max + 4), "{:?}", err);
+        assert!(parse_str_with(&nested(max + 1), &ParseOptions { max_depth: max + 1 }).is_ok());
+    }
+
+    #[test]
+    fn pathological_nesting_is_an_error() {
+        let input = format!("{}{}", "{".repeat(10_000), "}".repeat(10_000));
+        assert!(matches!(parse_str(&input), Err(ParseError::TooDeep { .. })));
+        let (items, diagnostics) = parse_str_recover(&format!("a = 1\n{}\nb = 2", input));
+        assert_eq!(items.len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(parse_items(&input).last(), Some(Err(ParseError::TooDeep { .. }))));
+    }
+
+    #[test]
+    fn scripts_parse_with_from_str() {
+        fn read(text: &str) -> Result<Vec<Item>, ParseError> {
+            let script: Script = text.parse()?;
+            Ok(script.into())
+        }
+        assert_eq!(read("a = { b = yes }").unwrap(), parse_str("a = { b = yes }").unwrap());
+        assert!(matches!(read("a = {"), Err(ParseError::UnbalancedBraces { .. })));
+        let script = "a=1 b={c=2}".parse::<Script>().unwrap();
+        assert_eq!(script.to_string(), "a = 1\nb = {\n  c = 2\n}\n");
+        assert_eq!(script.to_string().parse::<Script>().unwrap(), script);
+    }
+
+    #[test]
+    fn windows_paths_and_crlf_strings_round_trip() {
+        let path = r#"icon = "C:\Games\hoi4\gfx\flags\GER.tga""#;
+        assert_eq!(round_trip(path), format!("{}\n", path));
+        assert_eq!(only_value(&parse_str(path).unwrap()), &Value::Atom(Atom::String(r"C:\Games\hoi4\gfx\flags\GER.tga".to_string())));
+        // A line break inside a string of a CRLF file is escaped whole, and the file keeps its endings
+        let input = "desc = \"line one\r\nline two\"\r\nb = 1\r\n";
+        let opts = SerializeOptions { line_ending: LineEnding::detect(input), ..SerializeOptions::default() };
+        let items = parse_str(input).unwrap();
+        assert_eq!(only_value(&items[..1]), &Value::Atom(Atom::String("line one\r\nline two".to_string())));
+        let out = serialize_ast_with(&items, &opts);
+        assert_eq!(out, "desc = \"line one\\r\\nline two\"\r\nb = 1\r\n");
+        assert_eq!(parse_str(&out).unwrap(), items);
+        assert_eq!(serialize_ast_with(&parse_str(&out).unwrap(), &opts), out);
+    }
+
+
+    #[test]
+    fn not_equal_round_trips() {
+        let out = round_trip("limit = { tag != GER num_of_factories!=10 }");
+        assert_eq!(out, "limit = {\n  tag != GER\n  num_of_factories != 10\n}\n");
+        let items = parse_str(&out).unwrap();
+        let Value::Block(inner) = only_value(&items) else { panic!("{:?}", items) };
+        assert!(inner.iter().all(|it| matches!(it, Item::Pair { op: Operator::Ne, .. })));
+    }
+
+
+    #[test]
+    fn optional_assignments_round_trip() {
+        let input = include_str!("../tests/fixtures/optional.txt");
+        let out = round_trip(input);
+        assert_eq!(out.matches(" ?= ").count(), 3, "{}", out);
+        assert!(out.contains("factor ?= 1"));
+        let opts = SerializeOptions { indent: Indent::Tabs, ..Default::default() };
+        assert_eq!(serialize_ast_with(&parse_str(input).unwrap(), &opts), input.replace("{ factor?=1 }", "{\n\t\tfactor ?= 1\n\t}").replace("{ has_war != yes }", "{\n\t\thas_war != yes\n\t}"));
+    }
+
+
+    #[test]
+    fn parse_errors_name_line_and_column() {
+        let err = parse_str("a = 1\nb = {\n  c = = 2\n}\n").unwrap_err();
+        assert!(matches!(err, ParseError::Pest(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("parse error at line 3, col 7: "), "{}", err);
+        let err = parse_str("a = 1\n  b = <").unwrap_err();
+        assert!(err.to_string().starts_with("parse error at line 2, col 7: "), "{}", err);
+    }
+
+
+    #[test]
+    fn indent_width_is_configurable() {
+        let items = parse_str("a = { b = { c = 1 } d = { 1 2 } }").unwrap();
+        let with = |indent| serialize_ast_with(&items, &SerializeOptions { indent, ..Default::default() });
+        assert_eq!(with(Indent::default()), "a = {\n  b = {\n    c = 1\n  }\n  d = {\n    1 2\n  }\n}\n");
+        assert_eq!(with(Indent::Spaces(4)), "a = {\n    b = {\n        c = 1\n    }\n    d = {\n        1 2\n    }\n}\n");
+        assert_eq!(with(Indent::Tabs), "a = {\n\tb = {\n\t\tc = 1\n\t}\n\td = {\n\t\t1 2\n\t}\n}\n");
+        assert_eq!(parse_str(&with(Indent::Spaces(4))).unwrap(), items);
+    }
+
+
+    #[test]
+    fn array_wrap_width_is_configurable() {
+        let numbers: Vec<String> = (100..130).map(|n| n.to_string()).collect();
+        let items = parse_str(&format!("a = {{ {} }}", numbers.join(" "))).unwrap();
+        let with = |array_wrap_width| serialize_ast_with(&items, &SerializeOptions { array_wrap_width, ..Default::default() });
+        let wrapped = with(40);
+        let rows: Vec<&str> = wrapped.lines().skip(1).take_while(|l| *l != "}").collect();
+        // Ten 3-digit numbers and their separators make 39 characters
+        assert_eq!(rows, ["  100 101 102 103 104 105 106 107 108 109", "  110 111 112 113 114 115 116 117 118 119", "  120 121 122 123 124 125 126 127 128 129"]);
+        assert_eq!(with(usize::MAX), format!("a = {{\n  {}\n}}\n", numbers.join(" ")));
+        assert_eq!(parse_str(&wrapped).unwrap(), items);
+    }
+
+
+    #[test]
+    fn small_blocks_inline_under_the_limit() {
+        let items = parse_str("allowed = { has_dlc = yes }\ntags = { GER ITA }\nlimit = { has_dlc = yes tag = GER }\nnote = { # why\n  x = 1\n}\n").unwrap();
+        let opts = SerializeOptions { inline_blocks_under: Some(20), ..Default::default() };
+        let out = serialize_ast_with(&items, &opts);
+        // `{ has_dlc = yes }` is 17 characters; the two-pair block is over the limit and the commented one never inlines
+        assert_eq!(out, "allowed = { has_dlc = yes }\ntags = { GER ITA }\nlimit = {\n  has_dlc = yes\n  tag = GER\n}\nnote = {\n  # why\n  x = 1\n}\n");
+        assert_eq!(parse_str(&out).unwrap(), items);
+        let tight = serialize_ast_with(&items, &SerializeOptions { inline_blocks_under: Some(17), ..Default::default() });
+        assert!(tight.starts_with("allowed = {\n  has_dlc = yes\n}\ntags = { GER ITA }\n"), "{}", tight);
+    }
+
+
+    #[test]
+    fn operators_align_within_a_block() {
+        let items = parse_str("a = { id = 1 cost = 10 ai_will_do = 5 reward = { x = 1 } }").unwrap();
+        let aligned = serialize_ast_with(&items, &SerializeOptions { align_operators: true, ..Default::default() });
+        assert_eq!(aligned, "a = {\n  id         = 1\n  cost       = 10\n  ai_will_do = 5\n  reward = {\n    x = 1\n  }\n}\n");
+        assert_eq!(parse_str(&aligned).unwrap(), items);
+        assert_eq!(serialize_ast(&items), "a = {\n  id = 1\n  cost = 10\n  ai_will_do = 5\n  reward = {\n    x = 1\n  }\n}\n");
+    }
+
+
+    #[test]
+    fn pathological_numbers_never_panic() {
+        // Deterministic pseudo-random tokens built from the characters numbers are made of
+        let alphabet = b"0123456789.eE+-";
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut tokens = vec!["1e400".to_string(), "-1E999".to_string(), "9".repeat(400), format!("0.{}1", "0".repeat(400)), "1e-400".to_string(), "+0".to_string(), "-0.0e0".to_string()];
+        for _ in 0..2000 {
+            let len = 1 + (seed % 12) as usize;
+            let token: String = (0..len).map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                alphabet[(seed % alphabet.len() as u64) as usize] as char
+            }).collect();
+            tokens.push(token);
+        }
+        for token in &tokens {
+            let input = format!("a = {}\n{} = {{ {} }}", token, token, token);
+            if let Ok(items) = parse_str(&input) {
+                assert_eq!(parse_str(&serialize_ast(&items)).unwrap(), items, "{}", token);
+            }
+            let _ = parse_str_borrowed(&input);
+        }
+        for token in ["1e400", "-1E999"] {
+            let err = parse_str(&format!("a = {}", token)).unwrap_err();
+            assert!(matches!(&err, ParseError::InvalidNumber { text, line: 1, col: 5 } if text == token), "{:?}", err);
+        }
+        assert!(matches!(parse_str(&format!("a = {}", "9".repeat(400))), Err(ParseError::InvalidNumber { .. })));
+        assert_eq!(parse_str("a = 1e-400").unwrap(), parse_str("a = 0").unwrap());
+    }
+
+
+    #[test]
+    fn dates_sort_chronologically() {
+        let texts = ["1939.9.1.12", "1936.1.1", "1939.9.1", "1939.9.1.0", "1939.10.1", "1939.9.1.6.30", "1850.12.31", "1939.9.1.6"];
+        let mut dates: Vec<Date> = texts.iter().map(|t| parse_date_str(t).unwrap()).collect();
+        dates.sort();
+        let sorted: Vec<String> = dates.iter().map(fmt_date).collect();
+        // Without an hour a date sorts before the same day at hour 0
+        assert_eq!(sorted, ["1850.12.31", "1936.1.1", "1939.9.1", "1939.9.1.0", "1939.9.1.6", "1939.9.1.6.30", "1939.9.1.12", "1939.10.1"]);
+        assert!(parse_date_str("1939.09.01").unwrap() == parse_date_str("1939.9.1").unwrap());
+    }
+
+
+    #[test]
+    fn built_value_lists_match_parsed_ones() {
+        let parsed = parse_str("tags = { GER 1936.1.1 2 yes \"a b\" }").unwrap();
+        let atoms: Vec<Atom> = vec!["GER".into(), "1936.1.1".into(), 2.0.into(), "yes".into(), "a b".into()];
+        let values = atoms.into_iter().map(|a| Item::Value { value: Value::Atom(a), trailing_comment: None }).collect();
+        let built = vec![Item::Pair { key: KeyAtom::Ident("tags".to_string()), op: Operator::Eq, value: Value::from_items(values), trailing_comment: None, leading_comments: Vec::new() }];
+        assert!(matches!(only_value(&built), Value::Array(a) if a.len() == 5));
+        assert_eq!(built, parsed);
+        assert_eq!(serialize_ast(&built), serialize_ast(&parsed));
+        assert_eq!(Value::from_items(Vec::new()), Value::Array(Vec::new()));
+    }
+
+
+    #[test]
+    fn final_newline_can_be_left_off() {
+        let items = parse_str("a = 1\nb = { c = 2 }\n").unwrap();
+        let with = |final_newline| serialize_ast_with(&items, &SerializeOptions { final_newline, ..Default::default() });
+        assert_eq!(with(true), "a = 1\nb = {\n  c = 2\n}\n");
+        assert_eq!(with(false), "a = 1\nb = {\n  c = 2\n}");
+        let crlf = serialize_ast_with(&items, &SerializeOptions { final_newline: false, line_ending: LineEnding::Crlf, ..Default::default() });
+        assert_eq!(crlf, "a = 1\r\nb = {\r\n  c = 2\r\n}");
+        // An empty file stays empty either way
+        let empty = parse_str("").unwrap();
+        for final_newline in [true, false] {
+            assert_eq!(serialize_ast_with(&empty, &SerializeOptions { final_newline, ..Default::default() }), "");
+        }
+    }
+
+
+    #[test]
+    fn semicolons_separate_items() {
+        let items = parse_str("a = 1; b = 2").unwrap();
+        assert_eq!(items, parse_str("a = 1\nb = 2").unwrap());
+        assert_eq!(round_trip("a = 1; b = 2"), "a = 1\nb = 2\n");
+        assert_eq!(round_trip("x = { a = 1; b = { 1; 2 }; };"), "x = {\n  a = 1\n  b = {\n    1 2\n  }\n}\n");
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::path::NodePath;
+use crate::{key_text, Item, Value};
+
+// Find bare values sitting among pairs, usually a missing key or `=` (`factor 1`).
+// Values in a list without any pair (plain arrays, value-only blocks) are not reported.
+pub fn find_orphan_values(items: &[Item]) -> Vec<NodePath> {
+    let mut out = Vec::new();
+    orphans_in(items, &NodePath::default(), &mut out);
+    out
+}
+
+fn orphans_in(items: &[Item], path: &NodePath, out: &mut Vec<NodePath>) {
+    let has_pairs = items.iter().any(|it| matches!(it, Item::Pair { .. }));
+    for (i, it) in items.iter().enumerate() {
+        match it {
+            Item::Pair { key, value, .. } => {
+                if let Value::Block(inner) = value { orphans_in(inner, &path.child(i, Some(key_text(key))), out); }
+            }
+            Item::Value { value: v, .. } => {
+                if has_pairs { out.push(path.child(i, None)); }
+                if let Value::Block(inner) = v { orphans_in(inner, &path.child(i, None), out); }
+            }
+            Item::Comment(_) | Item::Blank(_) => {}
+        }
+    }
+}
+
+// Keys used by more than one pair directly in `items`, with how many times, in order of first use
+pub fn duplicate_keys_in_block(items: &[Item]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for it in items {
+        let Item::Pair { key, .. } = it else { continue };
+        let name = key_text(key);
+        match counts.iter_mut().find(|(k, _)| *k == name) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((name, 1)),
+        }
+    }
+    counts.retain(|(_, n)| *n > 1);
+    counts
+}
+
+// Find pairs overridden by a later pair with the same key in the same block, where the game
+// usually keeps only the last one. Keys meant to repeat (`add_ideas`, `focus`) are reported too.
+pub fn find_duplicate_keys(items: &[Item]) -> Vec<NodePath> {
+    let mut out = Vec::new();
+    duplicates_in(items, &NodePath::default(), &mut out);
+    out
+}
+
+fn duplicates_in(items: &[Item], path: &NodePath, out: &mut Vec<NodePath>) {
+    let keys: Vec<Option<String>> = items.iter().map(|it| match it { Item::Pair { key, .. } => Some(key_text(key)), _ => None }).collect();
+    let mut last: HashMap<&str, usize> = HashMap::new();
+    for (i, name) in keys.iter().enumerate() {
+        if let Some(name) = name { last.insert(name, i); }
+    }
+    for (i, it) in items.iter().enumerate() {
+        if let Some(name) = &keys[i] {
+            if last[name.as_str()] != i { out.push(path.child(i, Some(name.clone()))); }
+        }
+        match it {
+            Item::Pair { value: Value::Block(inner), .. } => duplicates_in(inner, &path.child(i, keys[i].clone()), out),
+            Item::Value { value: Value::Block(inner), .. } => duplicates_in(inner, &path.child(i, None), out),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    #[test]
+    fn reports_duplicated_keys() {
+        let items = parse_str("idea = { cost = 1 }\nother = 2\nidea = { cost = 2 cost = 3 }\n").unwrap();
+        assert_eq!(duplicate_keys_in_block(&items), [("idea".to_string(), 2)]);
+        let paths: Vec<String> = find_duplicate_keys(&items).iter().map(ToString::to_string).collect();
+        // The earlier pair of each duplicate is the dead one
+        assert_eq!(paths, ["idea", "idea.cost"]);
+        assert_eq!(find_duplicate_keys(&items)[0].0[0].index, 0);
+    }
+
+    #[test]
+    fn reports_values_among_pairs() {
+        let items = parse_str("modifier = { factor = 1 2 }\nlist = { 1 2 3 }").unwrap();
+        let paths: Vec<String> = find_orphan_values(&items).iter().map(ToString::to_string).collect();
+        assert_eq!(paths, ["modifier[1]"]);
+    }
+}
@@ -0,0 +1,90 @@
+use pest::iterators::Pair;
+
+use crate::path::NodePath;
+use crate::{blank_lines, key_text, parse_key, parse_raw, Rule, BOM};
+
+// Path of the innermost pair, value or comment containing byte `offset` of `input`, indexed the way
+// parse_str lays out its items (so it can be looked up in that AST). An offset inside a block but between
+// its items gives the pair holding the block, and a trailing comment belongs to its pair or value.
+// None when the offset falls between top-level items, inside a leading BOM, or the input does not parse.
+pub fn node_at(input: &str, offset: usize) -> Option<NodePath> {
+    // parse_raw strips a BOM, so its spans start after it
+    let offset = if input.starts_with(BOM) { offset.checked_sub(BOM.len_utf8())? } else { offset };
+    let file = parse_raw(input).ok()?.next()?;
+    let body = file.into_inner().find(|p| p.as_rule() == Rule::body)?;
+    find_in_body(body, offset, &NodePath::default(), true)
+}
+
+fn find_in_body(body: Pair<Rule>, offset: usize, parent: &NodePath, top_level: bool) -> Option<NodePath> {
+    let input = body.as_span().get_input();
+    let mut index = 0;
+    // End and path of the previous pair or value, while a trailing comment can still attach to it
+    let mut prev: Option<(usize, NodePath)> = None;
+    // End of the previous item, for blank-line runs between top-level items
+    let mut last_end = None;
+    for item in body.into_inner() {
+        let span = item.as_span();
+        let contains = span.start() <= offset && offset < span.end();
+        let node = item.into_inner().next()?;
+        let is_comment = node.as_rule() == Rule::comment;
+        if is_comment {
+            if let Some((end, path)) = prev.take() {
+                if !input[end..span.start()].contains('\n') {
+                    if contains { return Some(path); }
+                    last_end = Some(span.end());
+                    continue;
+                }
+            }
+        }
+        if let (true, Some(end)) = (top_level, last_end) {
+            if blank_lines(&input[end..span.start()]).is_some() { index += 1; }
+        }
+        last_end = Some(span.end());
+        let (path, block) = match node.as_rule() {
+            Rule::pair | Rule::bare_pair => {
+                let mut parts = node.into_inner();
+                let key = key_text(&parse_key(parts.next()?).ok()?);
+                let value = parts.last()?;
+                let block = if value.as_rule() == Rule::block { Some(value) } else { value.into_inner().next().filter(|v| v.as_rule() == Rule::block) };
+                (parent.child(index, Some(key)), block)
+            }
+            _ => (parent.child(index, None), node.into_inner().next().filter(|v| v.as_rule() == Rule::block)),
+        };
+        if contains {
+            let body = block.and_then(|b| b.into_inner().find(|p| p.as_rule() == Rule::body));
+            return Some(body.and_then(|b| find_in_body(b, offset, &path, false)).unwrap_or(path));
+        }
+        prev = if is_comment { None } else { Some((span.end(), path)) };
+        index += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_at(input: &str, needle: &str) -> Option<String> {
+        node_at(input, input.find(needle).unwrap()).map(|p| p.to_string())
+    }
+
+    #[test]
+    fn finds_the_innermost_node() {
+        let input = "a = 1\n\nidea = {\n  cost = 150 # base\n  modifier = { stability_factor = 0.1 }\n  yes\n}\n";
+        assert_eq!(path_at(input, "0.1").as_deref(), Some("idea.modifier.stability_factor"));
+        assert_eq!(path_at(input, "# base").as_deref(), Some("idea.cost"));
+        assert_eq!(path_at(input, "yes").as_deref(), Some("idea[2]"));
+        assert_eq!(path_at(input, "a = 1").as_deref(), Some("a"));
+        // The blank line is item 1, so the block is item 2
+        assert_eq!(node_at(input, input.find("cost").unwrap()).unwrap().0[0].index, 2);
+        assert_eq!(node_at(input, 5), None);
+    }
+
+    #[test]
+    fn offsets_count_a_leading_bom() {
+        let input = "\u{feff}a = b\nc";
+        assert_eq!(path_at(input, "b").as_deref(), Some("a"));
+        assert_eq!(path_at(input, "c").as_deref(), Some("[1]"));
+        assert_eq!(node_at(input, 1), None);
+    }
+}
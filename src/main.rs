@@ -1,305 +1,87 @@
-use std::fs;
-use std::path::Path;
-use pest::Parser;
-use pest::iterators::{Pair, Pairs};
-use pest_derive::Parser;
-
-// Derive parser for Paradox script grammar
-#[derive(Parser)]
-#[grammar = "hoi4.pest"]
-struct HoiParser;
-
-// Operator types (assignment and comparisons)
-#[derive(Debug, Clone)]
-enum Operator { Eq, Le, Ge, Lt, Gt }
-
-// Date type (YYYY.MM.DD(.HH))
-#[derive(Debug, Clone)]
-struct Date { y: u32, m: u8, d: u8, h: Option<u8> }
-
-// Atomic values: string, identifier, number, date, boolean
-#[derive(Debug, Clone)]
-enum Atom { String(String), Ident(String), Number(f64), Date(Date), Bool(bool) }
-
-// Key types: identifier, number, date
-#[derive(Debug, Clone)]
-enum KeyAtom { Ident(String), Number(f64), Date(Date) }
-
-// Value types: atom, array (plain value list), or block (with key/values and comments)
-#[derive(Debug, Clone)]
-enum Value { Atom(Atom), Array(Vec<Atom>), Block(Vec<Item>) }
-
-// Item: key-value pair, standalone value, or comment
-#[derive(Debug, Clone)]
-enum Item { Pair { key: KeyAtom, op: Operator, value: Value }, ValueItem(Value), Comment(String) }
-
-// Parse date string into struct
-fn parse_date_str(s: &str) -> Date {
-    let mut parts = s.split('.');
-    let y = parts.next().unwrap().parse::<u32>().unwrap();
-    let m = parts.next().unwrap().parse::<u8>().unwrap();
-    let d = parts.next().unwrap().parse::<u8>().unwrap();
-    let h = parts.next().map(|x| x.parse::<u8>().unwrap());
-    Date { y, m, d, h }
-}
-
-// Parse operator
-fn parse_operator(p: Pair<Rule>) -> Operator {
-    match p.as_str() {
-        "=" => Operator::Eq,
-        "<=" => Operator::Le,
-        ">=" => Operator::Ge,
-        "<" => Operator::Lt,
-        ">" => Operator::Gt,
-        _ => Operator::Eq,
-    }
-}
-
-// Parse atomic value
-fn parse_atom(p: Pair<Rule>) -> Atom {
-    match p.as_rule() {
-        Rule::string => {
-            let inner = p.into_inner().next().unwrap();
-            let s = inner.as_str();
-            // Recognize date-like pattern (YYYY.MM.DD(.HH)) within quoted strings
-            if let Some(d) = try_parse_date_like(s) { return Atom::Date(d); }
-            Atom::String(s.to_string())
-        }
-        Rule::identifier => Atom::Ident(p.as_str().to_string()),
-        Rule::number => Atom::Number(p.as_str().parse::<f64>().unwrap()),
-        Rule::date => Atom::Date(parse_date_str(p.as_str())),
-        Rule::boolean => Atom::Bool(p.as_str() == "yes"),
-        _ => Atom::Ident(p.as_str().to_string()),
-    }
-}
-
-// Parse key
-fn parse_key(p: Pair<Rule>) -> KeyAtom {
-    match p.as_rule() {
-        Rule::identifier => KeyAtom::Ident(p.as_str().to_string()),
-        Rule::number => KeyAtom::Number(p.as_str().parse::<f64>().unwrap()),
-        Rule::date => KeyAtom::Date(parse_date_str(p.as_str())),
-        _ => KeyAtom::Ident(p.as_str().to_string()),
-    }
-}
-
-// Try to detect a date-like pattern from string content
-fn try_parse_date_like(s: &str) -> Option<Date> {
-    let parts: Vec<&str> = s.split('.').collect();
-    if parts.len() != 3 && parts.len() != 4 { return None; }
-    fn all_digits(x: &str) -> bool { x.chars().all(|c| c.is_ascii_digit()) }
-    if !(all_digits(parts[0]) && (3..=4).contains(&parts[0].len())) { return None; }
-    for i in 1..parts.len() {
-        if !all_digits(parts[i]) || !(1..=2).contains(&parts[i].len()) { return None; }
-    }
-    let y = parts[0].parse::<u32>().ok()?;
-    let m = parts[1].parse::<u8>().ok()?;
-    let d = parts[2].parse::<u8>().ok()?;
-    let h = if parts.len() == 4 { Some(parts[3].parse::<u8>().ok()?) } else { None };
-    Some(Date { y, m, d, h })
-}
-
-// Parse block:
-// - If all children are plain atomic values (no pair/comment), classify as Array(Vec<Atom>)
-// - Otherwise as Block(Vec<Item>)
-fn parse_block(p: Pair<Rule>) -> Value {
-    let mut items: Vec<Item> = Vec::new();
-    let mut atoms: Vec<Atom> = Vec::new();
-    let mut only_atoms = true;
-    for child in p.into_inner() {
-        if child.as_rule() == Rule::body {
-            for it in child.into_inner() {
-                // Parse item uniformly and determine if it is plain atomic
-                let parsed = parse_item(it.clone());
-                match parsed {
-                    Item::ValueItem(Value::Atom(a)) => atoms.push(a),
-                    Item::ValueItem(Value::Array(_)) | Item::ValueItem(Value::Block(_)) | Item::Pair { .. } | Item::Comment(_) => {
-                        items.push(parsed);
-                        only_atoms = false;
-                    }
-                }
+use std::io::{self, Read, Write};
+use std::{env, fs, process};
+
+use clausewitz_script_parser::{format_str, parse_file, parse_str, serialize_ast, write_file, FormatOptions, Item, ParseError, Severity};
+
+const USAGE: &str = "usage: clausewitz-script-parser [<input>] [--ast <path>] [--out <path>]\n       clausewitz-script-parser --format";
+
+// Command line: input file (stdin when absent), optional AST dump, output file (stdout when absent).
+// `--format` is the stdin-to-stdout filter for editors and hooks, and takes no paths.
+#[derive(Default)]
+struct Args { input: Option<String>, ast: Option<String>, out: Option<String>, format: bool }
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut it = env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--ast" => args.ast = Some(it.next().ok_or("--ast needs a path")?),
+            "--out" => args.out = Some(it.next().ok_or("--out needs a path")?),
+            "--format" => args.format = true,
+            "--loc" => return Err("localisation files are not supported".to_string()),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
             }
+            s if s.starts_with("--") => return Err(format!("unknown option '{}'", s)),
+            _ if args.input.is_none() => args.input = Some(arg),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
         }
     }
-    if only_atoms { Value::Array(atoms) } else { Value::Block(items) }
-}
-
-// Parse item (pair or plain value)
-fn parse_item(p: Pair<Rule>) -> Item {
-    match p.as_rule() {
-        // Item is a wrapper node; inside is either a pair or a value
-        Rule::item => {
-            let mut inner = p.into_inner();
-            if let Some(child) = inner.next() { return parse_item(child); }
-            Item::ValueItem(Value::Atom(Atom::Ident(String::new())))
-        }
-        Rule::pair => {
-            let mut it = p.into_inner();
-            let key = parse_key(it.next().unwrap());
-            let op = parse_operator(it.next().unwrap());
-            let val_pair = it.next().unwrap();
-            let value = match val_pair.as_rule() {
-                // The third part of a pair is a wrapped value; unwrap into a concrete type
-                Rule::value => {
-                    let mut inner = val_pair.into_inner();
-                    let v = inner.next().unwrap();
-                    match v.as_rule() {
-                        Rule::block => parse_block(v),
-                        Rule::string | Rule::date | Rule::number | Rule::boolean | Rule::identifier => Value::Atom(parse_atom(v)),
-                        _ => Value::Atom(Atom::Ident(v.as_str().to_string())),
-                    }
-                }
-                Rule::block => parse_block(val_pair),
-                Rule::string | Rule::date | Rule::number | Rule::boolean | Rule::identifier => Value::Atom(parse_atom(val_pair)),
-                _ => Value::Atom(Atom::Ident(val_pair.as_str().to_string())),
-            };
-            Item::Pair { key, op, value }
-        }
-        Rule::value => {
-            let mut inner = p.into_inner();
-            let v = inner.next().unwrap();
-            let val = match v.as_rule() {
-                Rule::block => parse_block(v),
-                Rule::string | Rule::date | Rule::number | Rule::boolean | Rule::identifier => Value::Atom(parse_atom(v)),
-                _ => Value::Atom(Atom::Ident(v.as_str().to_string())),
-            };
-            Item::ValueItem(val)
-        }
-        Rule::comment => Item::Comment(p.as_str().to_string()),
-        _ => Item::ValueItem(Value::Atom(Atom::Ident(p.as_str().to_string()))),
+    if args.format && (args.input.is_some() || args.ast.is_some() || args.out.is_some()) {
+        return Err("--format reads stdin and writes stdout; it takes no paths".to_string());
     }
+    Ok(args)
 }
 
-// Parse file into a list of items
-fn parse_file(pairs: Pairs<Rule>) -> Vec<Item> {
-    let mut items = Vec::new();
-    let file = pairs.into_iter().next().unwrap();
-    for child in file.into_inner() {
-        if child.as_rule() == Rule::body {
-            for it in child.into_inner() { items.push(parse_item(it)); }
+fn run(args: &Args) -> Result<(), ParseError> {
+    let ast: Vec<Item> = match &args.input {
+        Some(path) => parse_file(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            parse_str(&input)?
         }
+    };
+    if let Some(path) = &args.ast { fs::write(path, format!("{:#?}", ast))?; }
+    match &args.out {
+        Some(path) => write_file(path, &ast)?,
+        None => io::stdout().write_all(serialize_ast(&ast).as_bytes())?,
     }
-    items
-}
-
-// Format date
-fn fmt_date(d: &Date) -> String {
-    match d.h { Some(h) => format!("{}.{}.{}.{}", d.y, d.m, d.d, h), None => format!("{}.{}.{}", d.y, d.m, d.d) }
+    Ok(())
 }
 
-// Serialize atomic value
-fn serialize_atom(a: &Atom) -> String {
-    match a {
-        Atom::String(s) => format!("\"{}\"", s),
-        Atom::Ident(s) => s.clone(),
-        Atom::Number(n) => n.to_string(),
-        Atom::Date(d) => fmt_date(d),
-        Atom::Bool(b) => if *b { "yes".to_string() } else { "no".to_string() },
+// `--format`: stdin through format_str to stdout. On errors nothing is written to stdout,
+// every diagnostic goes to stderr, and the exit code is 1.
+fn format_stdin() -> i32 {
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        eprintln!("io error: {}", e);
+        return 1;
     }
-}
-
-// Serialize key
-fn serialize_key(k: &KeyAtom) -> String {
-    match k { KeyAtom::Ident(s) => s.clone(), KeyAtom::Number(n) => n.to_string(), KeyAtom::Date(d) => fmt_date(d) }
-}
-
-// Serialize value
-fn serialize_value(v: &Value, indent: usize) -> String {
-    match v {
-        Value::Atom(a) => serialize_atom(a),
-        Value::Array(arr) => {
-            // Soft-wrap array at 120 characters (single element over 120 uses its own line)
-            let rendered: Vec<String> = arr.iter().map(|a| serialize_atom(a)).collect();
-            let mut out = String::new();
-            out.push_str("{\n");
-            let mut line = String::new();
-            for (idx, elem) in rendered.iter().enumerate() {
-                let sep = if line.is_empty() { "" } else { " " };
-                let prospective_len = line.len() + sep.len() + elem.len();
-                if !line.is_empty() && prospective_len > 120 {
-                    out.push_str(&" ".repeat(indent + 2));
-                    out.push_str(&line);
-                    out.push('\n');
-                    line.clear();
-                }
-                if line.is_empty() {
-                    line.push_str(elem);
-                } else {
-                    line.push(' ');
-                    line.push_str(elem);
-                }
-                // If it is the last element, output the current line
-                if idx == rendered.len() - 1 {
-                    out.push_str(&" ".repeat(indent + 2));
-                    out.push_str(&line);
-                    out.push('\n');
-                }
+    match format_str(&input, &FormatOptions::default()) {
+        Ok(out) => match io::stdout().write_all(out.as_bytes()) {
+            Ok(()) => 0,
+            Err(e) => { eprintln!("io error: {}", e); 1 }
+        },
+        Err(diagnostics) => {
+            for d in &diagnostics {
+                let level = match d.severity { Severity::Error => "error", Severity::Warning => "warning" };
+                eprintln!("{}: {}", level, d.message);
             }
-            out.push_str(&" ".repeat(indent));
-            out.push_str("}\n");
-            out
-        }
-        Value::Block(items) => {
-            let mut out = String::new();
-            out.push_str("{\n");
-            for it in items { out.push_str(&serialize_item(it, indent + 2)); }
-            out.push_str(&" ".repeat(indent));
-            out.push_str("}\n");
-            out
+            1
         }
     }
 }
 
-// Serialize item
-fn serialize_item(i: &Item, indent: usize) -> String {
-    match i {
-        Item::Pair { key, op, value } => {
-            let mut line = String::new();
-            line.push_str(&" ".repeat(indent));
-            line.push_str(&serialize_key(key));
-            line.push_str(" ");
-            line.push_str(match op { Operator::Eq => "=", Operator::Le => "<=", Operator::Ge => ">=", Operator::Lt => "<", Operator::Gt => ">" });
-            line.push_str(" ");
-            match value {
-                Value::Atom(_) => { line.push_str(&serialize_value(value, indent)); line.push('\n'); }
-                Value::Array(_) => { line.push_str(&serialize_value(value, indent)); }
-                Value::Block(_) => { line.push_str(&serialize_value(value, indent)); }
-            }
-            line
-        }
-        Item::ValueItem(v) => {
-            let mut line = String::new();
-            line.push_str(&" ".repeat(indent));
-            match v {
-                Value::Atom(_) => { line.push_str(&serialize_value(v, indent)); line.push('\n'); }
-                Value::Array(_) => { line.push_str(&serialize_value(v, indent)); line.push('\n'); }
-                Value::Block(_) => { line.push_str(&serialize_value(v, indent)); }
-            }
-            line
-        }
-        Item::Comment(s) => {
-            let mut line = String::new();
-            line.push_str(&" ".repeat(indent));
-            line.push_str(s);
-            line.push('\n');
-            line
-        }
-    }
-}
-
-// Serialize file
-fn serialize_file(items: &[Item]) -> String {
-    let mut out = String::new();
-    for it in items { out.push_str(&serialize_item(it, 0)); }
-    out
-}
-
 fn main() {
-    let input_path = Path::new("res/special.txt");
-    let input = fs::read_to_string(input_path).expect("cannot read file");
-    let pairs = HoiParser::parse(Rule::file, &input).expect("unsuccessful parse");
-    let ast = parse_file(pairs);
-    let _ = fs::create_dir_all("output");
-    fs::write("output/special.ast", format!("{:#?}", ast)).expect("write ast failed");
-    fs::write("output/special.txt", serialize_file(&ast)).expect("write txt failed");
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+    if args.format { process::exit(format_stdin()); }
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
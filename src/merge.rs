@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use crate::{key_text, Item, Value};
+
+// Overlay `overlay` onto `base`. The n-th pair with a given key in the overlay overrides the
+// n-th pair with that key in the base (so a single key overrides the first occurrence):
+// when both values are blocks they are merged recursively, otherwise the overlay pair replaces
+// the base pair whole, which means arrays are replaced rather than concatenated. Overlay pairs
+// without a counterpart, bare values and comments are appended.
+pub fn merge(base: &mut Vec<Item>, overlay: &[Item]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for it in overlay {
+        let Item::Pair { key, .. } = it else { base.push(it.clone()); continue };
+        let name = key_text(key);
+        let nth = seen.entry(name.clone()).or_insert(0);
+        let target = base.iter().enumerate()
+            .filter(|(_, b)| matches!(b, Item::Pair { key, .. } if key_text(key) == name))
+            .nth(*nth)
+            .map(|(i, _)| i);
+        *nth += 1;
+        match target {
+            Some(i) => merge_pair(&mut base[i], it),
+            None => base.push(it.clone()),
+        }
+    }
+}
+
+fn merge_pair(base: &mut Item, overlay: &Item) {
+    if let (Item::Pair { op, value: Value::Block(inner), trailing_comment, leading_comments, .. },
+            Item::Pair { op: new_op, value: Value::Block(patch), trailing_comment: new_comment, leading_comments: new_leading, .. }) = (&mut *base, overlay) {
+        *op = new_op.clone();
+        if new_comment.is_some() { trailing_comment.clone_from(new_comment); }
+        if !new_leading.is_empty() { leading_comments.clone_from(new_leading); }
+        merge(inner, patch);
+    } else {
+        *base = overlay.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, serialize_ast};
+
+    fn merged(base: &str, overlay: &str) -> String {
+        let mut items = parse_str(base).unwrap();
+        merge(&mut items, &parse_str(overlay).unwrap());
+        serialize_ast(&items)
+    }
+
+    #[test]
+    fn overrides_and_adds_keys() {
+        let out = merged("idea = { cost = 150 modifier = { stability_factor = 0.1 } }", "idea = { cost = 100 modifier = { war_support_factor = 0.05 } }");
+        assert_eq!(out, "idea = {\n  cost = 100\n  modifier = {\n    stability_factor = 0.1\n    war_support_factor = 0.05\n  }\n}\n");
+    }
+
+    #[test]
+    fn arrays_are_replaced_and_repeated_keys_pair_up_in_order() {
+        assert_eq!(merged("provinces = { 1 2 }", "provinces = { 3 }"), "provinces = {\n  3\n}\n");
+        assert_eq!(merged("a = 1\na = 2", "a = 3\na = 4\na = 5"), "a = 3\na = 4\na = 5\n");
+    }
+}
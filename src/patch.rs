@@ -0,0 +1,261 @@
+use crate::path::{NodePath, PathSegment};
+use crate::{key_text, Atom, Item, Value};
+
+// One step of a patch. A path's last segment indexes the list as it is when the step runs,
+// after the earlier steps' inserts and removals, so steps must be applied in order.
+// `Insert` puts `item` before the node at `path` (or at the end of the list); its last
+// segment's key names the new item rather than the node it goes before.
+// Inside an array the item is a bare atom value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    Set { path: NodePath, item: Item },
+    Insert { path: NodePath, item: Item },
+    Remove { path: NodePath },
+}
+
+// The changes turning one item list into another, in application order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch(pub Vec<PatchOp>);
+
+// Build the patch turning `a` into `b`. Unchanged items are matched first (longest common
+// subsequence), so moving an item only shifts indices instead of rewriting what follows it.
+// A changed pair is patched inside its block or array when the key stays the same.
+pub fn to_patch(a: &[Item], b: &[Item]) -> Patch {
+    let mut ops = Vec::new();
+    diff_list(a, b, &NodePath::default(), &mut ops);
+    Patch(ops)
+}
+
+// Apply `patch` in order. Fails with the path of the first step whose target does not exist
+// or whose key does not match; steps before it stay applied.
+pub fn apply_patch(items: &mut Vec<Item>, patch: &Patch) -> Result<(), NodePath> {
+    for op in &patch.0 {
+        let path = match op { PatchOp::Set { path, .. } | PatchOp::Insert { path, .. } | PatchOp::Remove { path } => path };
+        let Some((last, parent)) = path.0.split_last() else { return Err(path.clone()) };
+        let applied = match container(items, parent) {
+            Some(List::Items(list)) => apply_op(list, op, last, |it| Some(it.clone())),
+            Some(List::Atoms(list)) => apply_op(list, op, last, |it| match it {
+                Item::Value { value: Value::Atom(a), .. } => Some(a.clone()),
+                _ => None,
+            }),
+            None => false,
+        };
+        if !applied { return Err(path.clone()); }
+    }
+    Ok(())
+}
+
+// Elements of a list a patch can address: the items of a body, or the atoms of an array
+trait Node: PartialEq {
+    fn key(&self) -> Option<String>;
+    fn to_item(&self) -> Item;
+    // Whether `other` replaces this node in place rather than being a removal plus an insertion
+    fn same_slot(&self, other: &Self) -> bool;
+    // Steps changing this node into `other`, which sits at `path`
+    fn diff_into(&self, other: &Self, path: NodePath, ops: &mut Vec<PatchOp>);
+}
+
+impl Node for Item {
+    fn key(&self) -> Option<String> {
+        match self { Item::Pair { key, .. } => Some(key_text(key)), _ => None }
+    }
+
+    fn to_item(&self) -> Item { self.clone() }
+
+    fn same_slot(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Item::Pair { key: a, .. }, Item::Pair { key: b, .. }) => key_text(a) == key_text(b),
+            (Item::Value { .. }, Item::Value { .. }) | (Item::Comment(_), Item::Comment(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn diff_into(&self, other: &Self, path: NodePath, ops: &mut Vec<PatchOp>) {
+        let values = match (self, other) {
+            (Item::Pair { key: ka, op: oa, value: a, trailing_comment: ca, leading_comments: la },
+             Item::Pair { key: kb, op: ob, value: b, trailing_comment: cb, leading_comments: lb }) if ka == kb && oa == ob && ca == cb && la == lb => Some((a, b)),
+            (Item::Value { value: a, trailing_comment: ca }, Item::Value { value: b, trailing_comment: cb }) if ca == cb => Some((a, b)),
+            _ => None,
+        };
+        match values {
+            Some((Value::Block(a), Value::Block(b))) => diff_list(a, b, &path, ops),
+            Some((Value::Array(a), Value::Array(b))) => diff_list(a, b, &path, ops),
+            _ => ops.push(PatchOp::Set { path, item: other.clone() }),
+        }
+    }
+}
+
+impl Node for Atom {
+    fn key(&self) -> Option<String> { None }
+
+    fn to_item(&self) -> Item { Item::Value { value: Value::Atom(self.clone()), trailing_comment: None } }
+
+    fn same_slot(&self, _: &Self) -> bool { true }
+
+    fn diff_into(&self, other: &Self, path: NodePath, ops: &mut Vec<PatchOp>) {
+        ops.push(PatchOp::Set { path, item: other.to_item() });
+    }
+}
+
+// How an element of `a` maps onto `b`
+enum Step { Keep, Remove(usize), Insert(usize) }
+
+// Emit the steps turning list `a` (at `path`) into `b`. Between unchanged elements, removed
+// elements are paired in order with inserted ones in the same slot and patched in place.
+fn diff_list<T: Node>(a: &[T], b: &[T], path: &NodePath, ops: &mut Vec<PatchOp>) {
+    // Index into the list being rewritten: the already-patched prefix of `b`
+    let mut idx = 0;
+    let mut removed = Vec::new();
+    let mut inserted = Vec::new();
+    for step in align(a, b).into_iter().chain([Step::Keep]) {
+        match step {
+            Step::Remove(i) => removed.push(&a[i]),
+            Step::Insert(j) => inserted.push(&b[j]),
+            Step::Keep => {
+                let mut ins = inserted.drain(..).peekable();
+                for old in removed.drain(..) {
+                    match ins.next_if(|new| old.same_slot(new)) {
+                        Some(new) => {
+                            old.diff_into(new, path.child(idx, new.key()), ops);
+                            idx += 1;
+                        }
+                        None => ops.push(PatchOp::Remove { path: path.child(idx, old.key()) }),
+                    }
+                }
+                for new in ins {
+                    ops.push(PatchOp::Insert { path: path.child(idx, new.key()), item: new.to_item() });
+                    idx += 1;
+                }
+                idx += 1;
+            }
+        }
+    }
+}
+
+// Longest common subsequence alignment; the common prefix and suffix are skipped
+// before filling the table, so small edits to large lists stay cheap
+fn align<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Step> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    // lcs[i][j]: common subsequence length of ma[i..] and mb[j..]
+    let w = mb.len() + 1;
+    let mut lcs = vec![0u32; (ma.len() + 1) * w];
+    for i in (0..ma.len()).rev() {
+        for j in (0..mb.len()).rev() {
+            lcs[i * w + j] = if ma[i] == mb[j] { lcs[(i + 1) * w + j + 1] + 1 } else { lcs[(i + 1) * w + j].max(lcs[i * w + j + 1]) };
+        }
+    }
+    let mut steps: Vec<Step> = (0..prefix).map(|_| Step::Keep).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < ma.len() || j < mb.len() {
+        if i < ma.len() && j < mb.len() && ma[i] == mb[j] {
+            steps.push(Step::Keep);
+            i += 1;
+            j += 1;
+        } else if j == mb.len() || (i < ma.len() && lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+            steps.push(Step::Remove(prefix + i));
+            i += 1;
+        } else {
+            steps.push(Step::Insert(prefix + j));
+            j += 1;
+        }
+    }
+    steps.extend((0..suffix).map(|_| Step::Keep));
+    steps
+}
+
+// The list a patch step's parent path points at
+enum List<'a> { Items(&'a mut Vec<Item>), Atoms(&'a mut Vec<Atom>) }
+
+fn container<'a>(items: &'a mut Vec<Item>, parent: &[PathSegment]) -> Option<List<'a>> {
+    let Some((seg, rest)) = parent.split_first() else { return Some(List::Items(items)) };
+    let it = items.get_mut(seg.index)?;
+    if seg.key.is_some() && it.key() != seg.key { return None; }
+    let value = match it { Item::Pair { value, .. } | Item::Value { value, .. } => value, Item::Comment(_) | Item::Blank(_) => return None };
+    match value {
+        Value::Block(inner) => container(inner, rest),
+        Value::Array(atoms) if rest.is_empty() => Some(List::Atoms(atoms)),
+        _ => None,
+    }
+}
+
+// Run one step on the list it targets; `convert` turns the step's item into a list element
+fn apply_op<T: Node>(list: &mut Vec<T>, op: &PatchOp, seg: &PathSegment, convert: impl Fn(&Item) -> Option<T>) -> bool {
+    let target_ok = list.get(seg.index).is_some_and(|n| seg.key.is_none() || n.key() == seg.key);
+    match op {
+        PatchOp::Set { item, .. } if target_ok => match convert(item) {
+            Some(node) => { list[seg.index] = node; true }
+            None => false,
+        },
+        PatchOp::Remove { .. } if target_ok => { list.remove(seg.index); true }
+        PatchOp::Insert { item, .. } if seg.index <= list.len() => match convert(item) {
+            Some(node) => { list.insert(seg.index, node); true }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, serialize_ast};
+
+    const IDEAS: &str = include_str!("../tests/fixtures/ideas.txt");
+    const HISTORY: &str = include_str!("../tests/fixtures/history.txt");
+
+    fn patched(a: &str, b: &str) -> (Patch, Vec<Item>, Vec<Item>) {
+        let (mut items, target) = (parse_str(a).unwrap(), parse_str(b).unwrap());
+        let patch = to_patch(&items, &target);
+        apply_patch(&mut items, &patch).unwrap();
+        (patch, items, target)
+    }
+
+    #[test]
+    fn a_patch_between_fixtures_reproduces_the_target() {
+        for (a, b) in [(IDEAS, HISTORY), (HISTORY, IDEAS)] {
+            let (_, items, target) = patched(a, b);
+            assert_eq!(items, target);
+            assert_eq!(serialize_ast(&items), serialize_ast(&target));
+        }
+    }
+
+    #[test]
+    fn small_edits_patch_in_place() {
+        let edited = IDEAS.replace("cost = 150", "cost = 200\n\t\t\ttags = { b c d }").replace("\t\t\tpicture = generic_industry\n", "");
+        let source = IDEAS.replace("cost = 150", "cost = 150\n\t\t\ttags = { a b c }");
+        let (patch, items, target) = patched(&source, &edited);
+        assert_eq!(items, target);
+        let paths: Vec<String> = patch.0.iter().map(|op| match op {
+            PatchOp::Set { path, .. } => format!("set {}", path),
+            PatchOp::Insert { path, .. } => format!("insert {}", path),
+            PatchOp::Remove { path } => format!("remove {}", path),
+        }).collect();
+        // `d` goes in at 2, not 3: the removal of `a` has already shifted the array
+        assert_eq!(paths, [
+            "remove ideas.country.GER_rearmament.picture",
+            "set ideas.country.GER_rearmament.cost",
+            "remove ideas.country.GER_rearmament.tags[0]",
+            "insert ideas.country.GER_rearmament.tags[2]",
+        ]);
+    }
+
+    #[test]
+    fn array_index_changes_shift_later_steps() {
+        let (patch, items, target) = patched("tags = { a b c d }", "tags = { x b d e }");
+        assert_eq!(items, target);
+        assert!(patch.0.iter().all(|op| match op {
+            PatchOp::Set { path, .. } | PatchOp::Insert { path, .. } | PatchOp::Remove { path } => path.0.len() == 2,
+        }));
+        let (_, items, target) = patched("tags = { a b c }", "tags = { }");
+        assert_eq!(items, target);
+    }
+
+    #[test]
+    fn a_missing_target_fails_with_its_path() {
+        let mut items = parse_str("a = 1").unwrap();
+        let patch = to_patch(&parse_str("a = 1 b = 2").unwrap(), &parse_str("a = 1").unwrap());
+        assert_eq!(apply_patch(&mut items, &patch), Err(NodePath::default().child(1, Some("b".to_string()))));
+    }
+}
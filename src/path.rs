@@ -0,0 +1,32 @@
+use std::fmt;
+
+// One step into the tree: position of the item in its list, plus its key when it is a pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathSegment { pub index: usize, pub key: Option<String> }
+
+// Location of a node, from the top-level list down
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodePath(pub Vec<PathSegment>);
+
+impl NodePath {
+    // Extend the path by one segment
+    pub fn child(&self, index: usize, key: Option<String>) -> NodePath {
+        let mut segments = self.0.clone();
+        segments.push(PathSegment { index, key });
+        NodePath(segments)
+    }
+}
+
+// Render as dotted keys, with [i] for unkeyed items: `focus.completion_reward[3]`
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, seg) in self.0.iter().enumerate() {
+            match &seg.key {
+                Some(k) if i == 0 => write!(f, "{}", k)?,
+                Some(k) => write!(f, ".{}", k)?,
+                None => write!(f, "[{}]", seg.index)?,
+            }
+        }
+        Ok(())
+    }
+}
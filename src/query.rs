@@ -0,0 +1,96 @@
+use std::ops::Index;
+
+use crate::{key_name, Item, KeyAtom, Operator, Value};
+
+// Value at a dotted key path (`option.ai_chance.factor`), descending through block-valued pairs.
+// With repeated keys the first pair that leads to a match wins. Keys match by their text without
+// quotes (`my key.a` for `"my key" = { a = 1 }`), and keys containing dots (dates like `1936.1.1`) match as a whole.
+pub fn get_path<'a>(items: &'a [Item], path: &str) -> Option<&'a Value> {
+    items.iter().find_map(|it| {
+        let Item::Pair { key, value, .. } = it else { return None };
+        let name = key_name(key);
+        if path == name { return Some(value); }
+        let rest = path.strip_prefix(name.as_ref())?.strip_prefix('.')?;
+        match value { Value::Block(inner) => get_path(inner, rest), _ => None }
+    })
+}
+
+// `value["cost"]`: the value at a key path inside a block, as get_path finds it.
+// Panics when the value is not a block or the path is missing; use get_path for the fallible form.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, path: &str) -> &Value {
+        let found = match self { Value::Block(items) => get_path(items, path), _ => None };
+        found.unwrap_or_else(|| panic!("no key '{}' in value", path))
+    }
+}
+
+// The pairs of a block or file, skipping comments and bare values
+pub fn pairs(items: &[Item]) -> impl Iterator<Item = (&KeyAtom, &Operator, &Value)> {
+    items.iter().filter_map(|it| match it { Item::Pair { key, op, value, .. } => Some((key, op, value)), _ => None })
+}
+
+// Values of every pair with the given key, in order (for repeated keys such as `add_ideas`).
+// A quoted key matches its text without the quotes, as in get_path.
+pub fn values_of<'a>(items: &'a [Item], key: &'a str) -> impl Iterator<Item = &'a Value> {
+    pairs(items).filter(move |(k, _, _)| key_is(k, key)).map(|(_, _, v)| v)
+}
+
+fn key_is(k: &KeyAtom, name: &str) -> bool {
+    key_name(k) == name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    fn idea() -> Value {
+        let items = parse_str("idea = { cost = 150 modifier = { stability_factor = 0.1 } }").unwrap();
+        get_path(&items, "idea").unwrap().clone()
+    }
+
+    #[test]
+    fn index_finds_a_present_key() {
+        let idea = idea();
+        assert_eq!(idea["cost"], *get_path(&parse_str("a = 150").unwrap(), "a").unwrap());
+        assert_eq!(&idea["modifier.stability_factor"], get_path(&parse_str("a = 0.1").unwrap(), "a").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "no key 'removal_cost' in value")]
+    fn index_panics_on_a_missing_key() {
+        let _ = &idea()["removal_cost"];
+    }
+
+    #[test]
+    fn values_of_returns_repeated_keys_in_order() {
+        let items = parse_str("add_ideas = a\nother = b\n\"add_ideas\" = c").unwrap();
+        assert_eq!(values_of(&items, "add_ideas").count(), 2);
+        assert_eq!(pairs(&items).count(), 3);
+    }
+
+    const CHARACTERS: &str = include_str!("../tests/fixtures/characters.txt");
+
+    #[test]
+    fn get_path_descends_by_key() {
+        let items = parse_str(CHARACTERS).unwrap();
+        let skill = get_path(&items, "characters.GER_erwin_rommel.field_marshal.attack_skill");
+        assert_eq!(skill, get_path(&parse_str("a = 5").unwrap(), "a"));
+        assert!(matches!(get_path(&items, "characters.GER_erwin_rommel.field_marshal.traits"), Some(Value::Array(t)) if t.len() == 2));
+        assert_eq!(get_path(&items, "characters.GER_erwin_rommel.navy_leader.skill"), None);
+        assert_eq!(get_path(&items, "characters.GER_erwin_rommel.name.first"), None);
+        assert_eq!(get_path(&items, ""), None);
+    }
+
+    #[test]
+    fn quoted_keys_match_without_quotes() {
+        let items = parse_str(CHARACTERS).unwrap();
+        let skill = get_path(&items, "characters.GER hans von seeckt.corps_commander.skill");
+        assert_eq!(skill, get_path(&parse_str("a = 3").unwrap(), "a"));
+        assert_eq!(get_path(&items, "characters.\"GER hans von seeckt\".corps_commander.skill"), None);
+        let dates = parse_str("\"1939.9.1\" = { war = yes }").unwrap();
+        assert!(get_path(&dates, "1939.9.1.war").is_some());
+    }
+}
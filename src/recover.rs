@@ -0,0 +1,308 @@
+use std::{iter, mem};
+
+use pest::error::{Error, InputLocation};
+use pest::iterators::Pair;
+use pest::{Parser, Position};
+
+use crate::error::{BraceImbalance, Diagnostic, ParseError, Severity, Span};
+use crate::scan::{deep_braces, structure, too_deep};
+use crate::{attach_trailing_comment, blank_lines, parse_item, HoiParser, Item, ParseOptions, ParseState, Rule, BOM};
+
+// Parse as much as possible, collecting a diagnostic for every failed top-level item.
+// After an error, parsing resumes after the end of the broken item: the next newline
+// outside any brace it opened (or right after a stray `}`), so items before and after it are kept.
+// A key directly followed by a block (`key { ... }`) is read as `key = { ... }` with a warning.
+pub fn parse_str_recover(input: &str) -> (Vec<Item>, Vec<Diagnostic>) {
+    let (items, errors, missing) = parse_recovering(input);
+    let warnings = missing.into_iter().map(|span| (span, missing_operator(input, span), Severity::Warning));
+    let mut diagnostics: Vec<Diagnostic> = errors.into_iter()
+        .map(|(span, e)| (span, e, Severity::Error))
+        .chain(warnings)
+        .map(|(span, e, severity)| Diagnostic { span, message: e.to_string(), severity })
+        .collect();
+    diagnostics.sort_by_key(|d| d.span.start);
+    (items, diagnostics)
+}
+
+// Parse the whole input and report every error found, not just the first
+pub fn parse_str_all_errors(input: &str) -> Result<Vec<Item>, Vec<ParseError>> {
+    let (items, mut errors, missing) = parse_recovering(input);
+    errors.extend(missing.into_iter().map(|span| (span, missing_operator(input, span))));
+    errors.sort_by_key(|(span, _)| span.start);
+    if errors.is_empty() { Ok(items) } else { Err(errors.into_iter().map(|(_, e)| e).collect()) }
+}
+
+// Result of a parse that stopped at its first error: the items parsed before it, the warnings
+// for those items, and the error
+#[derive(Debug)]
+pub struct PartialParse { pub items: Vec<Item>, pub warnings: Vec<Diagnostic>, pub error: ParseError }
+
+// Parse top-level items up to the first error. Like parse_str_recover, a key directly followed by a block
+// (`key { ... }`) is read as `key = { ... }` with a warning. On failure, the items parsed so far are
+// returned alongside the error, so a half-typed file still shows its complete part.
+pub fn parse_str_lenient(input: &str) -> Result<(Vec<Item>, Vec<Diagnostic>), PartialParse> {
+    let mut items = Vec::new();
+    let mut warnings = Vec::new();
+    for result in stream(input, true) {
+        match result {
+            Ok((item, missing)) => {
+                items.push(item);
+                warnings.extend(missing.into_iter().map(|span| Diagnostic {
+                    span,
+                    message: missing_operator(input, span).to_string(),
+                    severity: Severity::Warning,
+                }));
+            }
+            Err(error) => return Err(PartialParse { items, warnings, error }),
+        }
+    }
+    Ok((items, warnings))
+}
+
+// Lazily parse top-level items one at a time, so a huge file never has to be held as a whole AST.
+// Accepts exactly what parse_str does; the first error is yielded as the last element.
+pub fn parse_items(input: &str) -> impl Iterator<Item = Result<Item, ParseError>> + '_ {
+    stream(input, false).map(|r| r.map(|(item, _)| item))
+}
+
+// Top-level items one at a time, each with the spans of keys it holds that miss their operator
+// (only ever non-empty when `lenient`). The first error ends the stream.
+fn stream(input: &str, lenient: bool) -> impl Iterator<Item = Result<(Item, Vec<Span>), ParseError>> + '_ {
+    let mut st = ParseState { lenient, ..ParseState::default() };
+    let deep: Vec<(usize, usize)> = deep_braces(input, ParseOptions::default().max_depth).collect();
+    let mut pos = Some(skip_whitespace(input, start(input)));
+    let mut last_end = None;
+    // An item held back while the blank lines before it are yielded
+    let mut pending = None;
+    iter::from_fn(move || {
+        if let Some(item) = pending.take() { return Some(Ok(item)); }
+        let at = pos.filter(|&p| p < input.len())?;
+        match parse_one(input, at, &deep, &mut st) {
+            Ok((item, end)) => {
+                let item = (item, mem::take(&mut st.missing_operators));
+                let blank = last_end.and_then(|prev| blank_lines(&input[prev..at]));
+                last_end = Some(end);
+                pos = Some(skip_whitespace(input, end));
+                Some(Ok(match blank {
+                    Some(b) => { pending = Some(item); (b, Vec::new()) }
+                    None => item,
+                }))
+            }
+            Err(e) => {
+                pos = None;
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+// Recovery loop behind parse_str_recover and parse_str_all_errors; also returns the spans of keys missing their operator
+fn parse_recovering(input: &str) -> (Vec<Item>, Vec<(Span, ParseError)>, Vec<Span>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    let mut st = ParseState { lenient: true, ..ParseState::default() };
+    let deep: Vec<(usize, usize)> = deep_braces(input, ParseOptions::default().max_depth).collect();
+    let mut pos = skip_whitespace(input, start(input));
+    // End of the previous item, while it parsed; blank lines next to a broken item are dropped
+    let mut last_end = None;
+    while pos < input.len() {
+        match parse_one(input, pos, &deep, &mut st) {
+            Ok((item, end)) => {
+                if let Some(blank) = last_end.and_then(|prev| blank_lines(&input[prev..pos])) { items.push(blank); }
+                items.push(item);
+                last_end = Some(end);
+                pos = end;
+            }
+            Err(err) => {
+                last_end = None;
+                let resume = resync(input, pos);
+                errors.push((Span { start: pos, end: resume }, err));
+                pos = resume;
+            }
+        }
+        pos = skip_whitespace(input, pos);
+    }
+    (items, errors, st.missing_operators)
+}
+
+// Parse one top-level item starting at `pos`; returns it with the offset just past it.
+// Spans recorded in `st` are made absolute. The grammar only sees the text up to the next brace nested
+// past the depth limit (from `deep`, see deep_braces), so it cannot recurse without bound.
+fn parse_one(input: &str, pos: usize, deep: &[(usize, usize)], st: &mut ParseState) -> Result<(Item, usize), ParseError> {
+    let next_deep = deep.get(deep.partition_point(|&(_, at)| at < pos)).copied();
+    let rest = &input[pos..next_deep.map_or(input.len(), |(_, at)| at)];
+    if rest.starts_with('}') {
+        return Err(ParseError::UnbalancedBraces { kind: BraceImbalance::Unexpected, line: line_of(input, pos) });
+    }
+    let pair = HoiParser::parse(Rule::item, rest)
+        .map_err(|e| match next_deep {
+            // Failing inside the too-deep group means the item is the one holding it
+            Some((group, at)) if pos + error_offset(&e) >= group => too_deep(at, ParseOptions::default().max_depth),
+            _ => relocate(input, pos, e),
+        })?
+        .next()
+        .unwrap();
+    let end = pos + pair.as_span().end();
+    // A pair that fails to parse degrades to its key as a bare value followed by the operator;
+    // report the pair's own error rather than accepting the fragment
+    if is_bare_value(&pair) && starts_with_operator(&input[skip_whitespace(input, end)..]) {
+        if let Err(e) = HoiParser::parse(Rule::pair, rest) { return Err(relocate(input, pos, e)); }
+    }
+    let recorded = st.missing_operators.len();
+    let mut item = parse_item(pair, st).map_err(|e| {
+        st.missing_operators.truncate(recorded);
+        shift(input, pos, e)
+    })?;
+    for span in &mut st.missing_operators[recorded..] {
+        span.start += pos;
+        span.end += pos;
+    }
+    // A comment on the same line belongs to the item
+    let after = end + (input[end..].len() - input[end..].trim_start_matches([' ', '\t', ';']).len());
+    if input[after..].starts_with('#') {
+        let len = input[after..].find(['\r', '\n']).unwrap_or(input.len() - after);
+        if attach_trailing_comment(Some(&mut item), &input[after..after + len]) { return Ok((item, after + len)); }
+    }
+    Ok((item, end))
+}
+
+fn is_bare_value(item: &Pair<Rule>) -> bool {
+    item.clone().into_inner().next().is_some_and(|p| p.as_rule() == Rule::value)
+}
+
+fn starts_with_operator(s: &str) -> bool {
+    ["=", "<", ">", "!=", "?="].iter().any(|op| s.starts_with(op))
+}
+
+// Error for a key at `span` that is directly followed by a block
+fn missing_operator(input: &str, span: Span) -> ParseError {
+    let (line, col) = Position::new(input, span.start).unwrap().line_col();
+    ParseError::MissingOperator { key: input[span.start..span.end].to_string(), line, col }
+}
+
+// Re-anchor an error from parsing `input[pos..]` onto the whole input, so line/col are absolute
+fn relocate(input: &str, pos: usize, e: Error<Rule>) -> ParseError {
+    let rel = error_offset(&e);
+    Error::new_from_pos(e.variant, Position::new(input, pos + rel).unwrap()).into()
+}
+
+// Re-anchor the line/col of an error found while building an item parsed from `input[pos..]`
+fn shift(input: &str, pos: usize, e: ParseError) -> ParseError {
+    let (base_line, base_col) = Position::new(input, pos).unwrap().line_col();
+    let at = |line: usize, col: usize| (base_line + line - 1, if line == 1 { base_col + col - 1 } else { col });
+    match e {
+        ParseError::InvalidDate { text, reason, line, col } => {
+            let (line, col) = at(line, col);
+            ParseError::InvalidDate { text, reason, line, col }
+        }
+        ParseError::InvalidNumber { text, line, col } => {
+            let (line, col) = at(line, col);
+            ParseError::InvalidNumber { text, line, col }
+        }
+        ParseError::InvalidColor { line, col } => {
+            let (line, col) = at(line, col);
+            ParseError::InvalidColor { line, col }
+        }
+        ParseError::MissingOperator { key, line, col } => {
+            let (line, col) = at(line, col);
+            ParseError::MissingOperator { key, line, col }
+        }
+        ParseError::MixedBlock { value, line, col } => {
+            let (line, col) = at(line, col);
+            ParseError::MixedBlock { value, line, col }
+        }
+        other => other,
+    }
+}
+
+// Offset where a pest error starts
+fn error_offset(e: &Error<Rule>) -> usize {
+    match e.location { InputLocation::Pos(p) => p, InputLocation::Span((p, _)) => p }
+}
+
+// Offset to resume from after a broken item starting at `pos`: past the first newline
+// or stray `}` that is not inside a brace the item opened
+fn resync(input: &str, pos: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in structure(&input[pos..]) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => return pos + i + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+// 1-based line number of a byte offset
+fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset].bytes().filter(|&b| b == b'\n').count() + 1
+}
+
+// Offset of the first item: past a leading BOM, so spans still index the input as given
+fn start(input: &str) -> usize {
+    if input.starts_with(BOM) { BOM.len_utf8() } else { 0 }
+}
+
+// Offset past whitespace and `;` separators
+fn skip_whitespace(input: &str, pos: usize) -> usize {
+    let rest = &input[pos..];
+    pos + (rest.len() - rest.trim_start_matches([' ', '\t', '\r', '\n', ';']).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Line and column an error reports
+    fn line_col(e: &ParseError) -> (usize, usize) {
+        match e {
+            ParseError::Pest(e) => match e.line_col { pest::error::LineColLocation::Pos(p) | pest::error::LineColLocation::Span(p, _) => p },
+            ParseError::InvalidDate { line, col, .. } | ParseError::InvalidNumber { line, col, .. } => (*line, *col),
+            _ => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn all_errors_are_reported_with_their_own_position() {
+        let input = "a = 1\nb = = 2\nc = { d = 1 }\ne = }\nf = 2\ng = { x = 1936.13.1 }\nh = 3\n";
+        let errors = parse_str_all_errors(input).unwrap_err();
+        let positions: Vec<(usize, usize)> = errors.iter().map(line_col).collect();
+        assert_eq!(positions, [(2, 5), (4, 5), (6, 11)]);
+        assert!(matches!(&errors[2], ParseError::InvalidDate { reason: "month out of range", .. }));
+    }
+
+    #[test]
+    fn recovery_keeps_the_items_around_errors() {
+        let (items, diagnostics) = parse_str_recover("a = 1\nb = = 2\nc = 3\n");
+        assert_eq!(items.len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span { start: 6, end: 14 });
+    }
+
+    #[test]
+    fn parse_items_yields_items_lazily() {
+        let input: String = (0..1000).map(|i| format!("key_{} = {{ value = {} }}\n", i, i)).collect();
+        assert_eq!(parse_items(&input).filter(Result::is_ok).count(), 1000);
+        // Items before an error are yielded; the error comes last and ends the iteration
+        let mut it = parse_items("a = 1\nb = = 2\nc = 3");
+        assert!(matches!(it.next(), Some(Ok(Item::Pair { .. }))));
+        assert!(matches!(it.next(), Some(Err(_))));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn lenient_mode_accepts_a_missing_operator_with_a_warning() {
+        let input = "a = 1\nx { b = 2 }";
+        assert!(matches!(crate::parse_str(input), Err(ParseError::MissingOperator { line: 2, col: 1, .. })));
+        assert!(matches!(parse_items(input).last(), Some(Err(ParseError::MissingOperator { .. }))));
+        let (items, warnings) = parse_str_lenient(input).unwrap();
+        assert_eq!(crate::serialize_ast(&items), "a = 1\nx = {\n  b = 2\n}\n");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].span, warnings[0].severity), (Span { start: 6, end: 7 }, Severity::Warning));
+        // Warnings for the items before an error are kept
+        let partial = parse_str_lenient("x { b = 2 }\ny = = 1").unwrap_err();
+        assert_eq!((partial.items.len(), partial.warnings.len()), (1, 1));
+    }
+}
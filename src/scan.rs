@@ -0,0 +1,112 @@
+use std::str::CharIndices;
+
+use crate::error::{BraceImbalance, ParseError, Span};
+
+// Iterator over the structural characters of script text: braces outside strings and comments,
+// plus every newline (so callers can track lines)
+pub(crate) struct Structure<'a> { chars: CharIndices<'a>, in_string: bool, in_comment: bool }
+
+pub(crate) fn structure(input: &str) -> Structure<'_> {
+    Structure { chars: input.char_indices(), in_string: false, in_comment: false }
+}
+
+impl Iterator for Structure<'_> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        while let Some((i, c)) = self.chars.next() {
+            if c == '\n' {
+                self.in_comment = false;
+                return Some((i, c));
+            }
+            if self.in_comment { continue; }
+            if self.in_string {
+                match c {
+                    // Skip the escaped character unless it is a newline, which is still reported
+                    '\\' => {
+                        if let Some((j, '\n')) = self.chars.next() { return Some((j, '\n')); }
+                    }
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => self.in_string = true,
+                '#' => self.in_comment = true,
+                '{' | '}' => return Some((i, c)),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+// Scan braces outside strings and comments; report the first imbalance
+pub(crate) fn check_braces(input: &str) -> Option<ParseError> {
+    let mut open_lines: Vec<usize> = Vec::new();
+    let mut line = 1;
+    for (_, c) in structure(input) {
+        match c {
+            '\n' => line += 1,
+            '{' => open_lines.push(line),
+            // The guard pops the matching `{`; nothing to pop means a stray `}`
+            '}' if open_lines.pop().is_none() => {
+                return Some(ParseError::UnbalancedBraces { kind: BraceImbalance::Unexpected, line });
+            }
+            _ => {}
+        }
+    }
+    open_lines.pop().map(|line| ParseError::UnbalancedBraces { kind: BraceImbalance::Unclosed, line })
+}
+
+// Top-level brace groups nested more than `max_depth` levels deep, as the offset of the group's
+// opening brace and of its first brace past the limit
+pub(crate) fn deep_braces(input: &str, max_depth: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut depth = 0usize;
+    let mut group = 0;
+    let mut reported = false;
+    structure(input).filter_map(move |(i, c)| {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth == 0 { reported = false; }
+        if c != '{' { return None; }
+        if depth == 1 { group = i; }
+        if depth <= max_depth || reported { return None; }
+        reported = true;
+        Some((group, i))
+    })
+}
+
+// Error for the brace at `offset`, one level past `max_depth`
+pub(crate) fn too_deep(offset: usize, max_depth: usize) -> ParseError {
+    ParseError::TooDeep { depth: max_depth + 1, span: Span { start: offset, end: offset + 1 } }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{BraceImbalance, ParseError};
+    use crate::parse_str;
+
+    #[test]
+    fn missing_close_brace_names_the_open_line() {
+        let err = parse_str("a = 1\nb = {\n  c = {\n    d = 1\n  }\n").unwrap_err();
+        assert!(matches!(err, ParseError::UnbalancedBraces { kind: BraceImbalance::Unclosed, line: 2 }));
+        assert_eq!(err.to_string(), "unclosed block opened at line 2");
+    }
+
+    #[test]
+    fn extra_close_brace_names_its_line() {
+        let err = parse_str("a = {\n  b = 1\n}\n}\nc = 2\n").unwrap_err();
+        assert!(matches!(err, ParseError::UnbalancedBraces { kind: BraceImbalance::Unexpected, line: 4 }));
+        assert_eq!(err.to_string(), "unexpected '}' at line 4");
+    }
+
+    #[test]
+    fn braces_in_strings_and_comments_are_ignored() {
+        assert!(parse_str("a = \"{\" # }\nb = { c = \"}\" }").is_ok());
+    }
+}
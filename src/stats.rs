@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+
+use crate::{key_text, Item, Value};
+
+// Summary counts for a whole tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AstStats {
+    pub pairs: usize,
+    // Comment lines plus trailing and leading comments
+    pub comments: usize,
+    pub blocks: usize,
+    pub arrays: usize,
+    // Deepest block or array nesting: 0 when there are none, 1 for `a = { ... }` at the top level
+    pub max_depth: usize,
+    // Number of different keys among the top-level pairs
+    pub distinct_top_level_keys: usize,
+}
+
+// Count pairs, comments, blocks and arrays through the whole tree
+pub fn stats(items: &[Item]) -> AstStats {
+    let mut st = AstStats::default();
+    count_items(items, 0, &mut st);
+    let keys: HashSet<String> = items.iter()
+        .filter_map(|it| match it { Item::Pair { key, .. } => Some(key_text(key)), _ => None })
+        .collect();
+    st.distinct_top_level_keys = keys.len();
+    st
+}
+
+fn count_items(items: &[Item], depth: usize, st: &mut AstStats) {
+    for it in items {
+        match it {
+            Item::Pair { value, trailing_comment, leading_comments, .. } => {
+                st.pairs += 1;
+                st.comments += leading_comments.len() + usize::from(trailing_comment.is_some());
+                count_value(value, depth, st);
+            }
+            Item::Value { value, trailing_comment } => {
+                st.comments += usize::from(trailing_comment.is_some());
+                count_value(value, depth, st);
+            }
+            Item::Comment(_) => st.comments += 1,
+            Item::Blank(_) => {}
+        }
+    }
+}
+
+fn count_value(value: &Value, depth: usize, st: &mut AstStats) {
+    match value {
+        Value::Block(inner) => {
+            st.blocks += 1;
+            st.max_depth = st.max_depth.max(depth + 1);
+            count_items(inner, depth + 1, st);
+        }
+        Value::Array(_) => {
+            st.arrays += 1;
+            st.max_depth = st.max_depth.max(depth + 1);
+        }
+        Value::Atom(_) | Value::Color { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    #[test]
+    fn counts_the_history_fixture() {
+        let items = parse_str(include_str!("../tests/fixtures/history.txt")).unwrap();
+        assert_eq!(stats(&items), AstStats { pairs: 13, comments: 0, blocks: 4, arrays: 0, max_depth: 2, distinct_top_level_keys: 5 });
+    }
+
+    #[test]
+    fn counts_comments_arrays_and_repeated_keys() {
+        let items = parse_str("# top\na = { 1 2 } # trailing\na = { b = { c = { 1 } } }\n").unwrap();
+        assert_eq!(stats(&items), AstStats { pairs: 4, comments: 2, blocks: 2, arrays: 2, max_depth: 3, distinct_top_level_keys: 1 });
+        assert_eq!(stats(&[]), AstStats::default());
+    }
+}
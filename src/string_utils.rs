@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+
+// Characters written as a backslash escape inside quotes, with the letter that follows the backslash
+pub const ESCAPE_SEQUENCES: [(char, char); 5] = [('\\', '\\'), ('"', '"'), ('\n', 'n'), ('\r', 'r'), ('\t', 't')];
+
+// Which characters escape_string_with and unescape_string_with treat as escapes.
+// The default is ESCAPE_SEQUENCES, as used by escape_string and unescape_string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeConfig {
+    // (character, letter after the backslash) pairs; characters not listed are written as they are
+    pub sequences: Cow<'static, [(char, char)]>,
+    // Also write every non-ASCII character as `\uXXXX` (a surrogate pair for characters beyond U+FFFF)
+    pub ascii_only: bool,
+}
+
+const DEFAULT_CONFIG: EscapeConfig = EscapeConfig { sequences: Cow::Borrowed(&ESCAPE_SEQUENCES), ascii_only: false };
+
+impl Default for EscapeConfig {
+    fn default() -> Self { DEFAULT_CONFIG }
+}
+
+impl EscapeConfig {
+    // Same config with `raw` written (and read back) literally
+    pub fn without(mut self, raw: char) -> Self {
+        self.sequences.to_mut().retain(|(c, _)| *c != raw);
+        self
+    }
+}
+
+// Escape text for writing between double quotes
+pub fn escape_string(s: &str) -> String {
+    escape_string_with(s, &DEFAULT_CONFIG)
+}
+
+// Escape text for writing between double quotes, also writing every non-ASCII character as `\uXXXX`
+pub fn escape_string_ascii(s: &str) -> String {
+    escape_string_with(s, &EscapeConfig { ascii_only: true, ..DEFAULT_CONFIG })
+}
+
+// Escape text for writing between double quotes, using the escapes in `cfg`. A backslash is only
+// escaped when the text after it would otherwise read as an escape, so an unknown escape kept by
+// unescape_string (`C:\dir`) is written back as it was.
+pub fn escape_string_with(s: &str, cfg: &EscapeConfig) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.char_indices() {
+        match cfg.sequences.iter().find(|(raw, _)| *raw == c) {
+            Some(('\\', _)) if reads_literally(&s[i + 1..], cfg) => out.push('\\'),
+            Some((_, letter)) => { out.push('\\'); out.push(*letter); }
+            None if cfg.ascii_only && !c.is_ascii() => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) { out.push_str(&format!("\\u{:04x}", unit)); }
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+// Whether a bare backslash followed by `rest` reads back as a backslash: `rest` does not start
+// an escape, and its first character is not written as one either
+fn reads_literally(rest: &str, cfg: &EscapeConfig) -> bool {
+    let Some(next) = rest.chars().next() else { return false };
+    if next == 'u' && unicode_escape(&rest[1..]).is_some() { return false; }
+    if cfg.ascii_only && !next.is_ascii() { return false; }
+    !cfg.sequences.iter().any(|(raw, letter)| *raw == next || *letter == next)
+}
+
+// Resolve backslash escapes in quoted text, including `\uXXXX` (surrogate pairs combined) and `\u{X...}`.
+// Unknown or malformed escapes and a trailing backslash are kept as written.
+pub fn unescape_string(s: &str) -> String {
+    unescape_string_with(s, &DEFAULT_CONFIG)
+}
+
+// Resolve the escapes in `cfg` and unicode escapes; any other escape is kept as written
+pub fn unescape_string_with(s: &str, cfg: &EscapeConfig) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let Some(next) = rest.chars().next() else { out.push('\\'); break };
+        if next == 'u' {
+            if let Some((c, len)) = unicode_escape(&rest[1..]) {
+                out.push(c);
+                rest = &rest[1 + len..];
+                continue;
+            }
+        }
+        match cfg.sequences.iter().find(|(_, letter)| *letter == next) {
+            Some((raw, _)) => out.push(*raw),
+            None => { out.push('\\'); out.push(next); }
+        }
+        rest = &rest[next.len_utf8()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Character of a `\u` escape from the text after the `u`, with the length of text it used.
+// A high surrogate only counts when the low half follows as another `\uXXXX`.
+fn unicode_escape(s: &str) -> Option<(char, usize)> {
+    if let Some(braced) = s.strip_prefix('{') {
+        let digits = &braced[..braced.find('}')?];
+        if digits.is_empty() || digits.len() > 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) { return None; }
+        let c = char::from_u32(u32::from_str_radix(digits, 16).ok()?)?;
+        return Some((c, digits.len() + 2));
+    }
+    let high = hex4(s)?;
+    if !(0xD800..0xDC00).contains(&high) { return char::from_u32(high).map(|c| (c, 4)); }
+    let low = hex4(s[4..].strip_prefix("\\u")?)?;
+    if !(0xDC00..0xE000).contains(&low) { return None; }
+    let c = char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))?;
+    Some((c, 10))
+}
+
+// Value of four leading hex digits
+fn hex4(s: &str) -> Option<u32> {
+    let digits = s.get(..4)?;
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) { return None; }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_unicode_escapes() {
+        assert_eq!(unescape_string(r"caf\u00e9 \u{1F600}"), "caf\u{e9} \u{1F600}");
+        // A surrogate pair combines into one character
+        assert_eq!(unescape_string(r"\ud83d\ude00"), "\u{1F600}");
+    }
+
+    #[test]
+    fn keeps_malformed_escapes_as_written() {
+        // A lone high surrogate stays as written; a valid escape after it is still decoded
+        for s in [r"\u00g1", r"\u12", r"\ud83d alone", r"\ud83d\u0041", r"\u{}", r"\u{110000}", r"\q", r"end\"] {
+            assert_eq!(unescape_string(s), s.replace(r"\u0041", "A"));
+        }
+    }
+
+    #[test]
+    fn ascii_escaping_round_trips() {
+        let s = "\u{e9} \"\u{1F600}\"\n";
+        assert_eq!(escape_string_ascii(s), r#"\u00e9 \"\ud83d\ude00\"\n"#);
+        assert_eq!(unescape_string(&escape_string_ascii(s)), s);
+        assert_eq!(unescape_string(&escape_string(s)), s);
+    }
+
+    #[test]
+    fn backslashes_are_escaped_only_where_needed() {
+        for (text, escaped) in [(r"C:\dir", r"C:\dir"), (r"a\", r"a\\"), (r"\n", r"\\n"), (r"\r", r"\\r"), ("\\\"", "\\\\\\\""), (r"\\", r"\\\\"), (r"\u0041", r"\\u0041"), (r"\ux", r"\ux")] {
+            assert_eq!(escape_string(text), escaped, "{}", text);
+            assert_eq!(unescape_string(&escape_string(text)), text);
+        }
+        assert_eq!(escape_string("a\r\nb"), r"a\r\nb");
+        assert_eq!(unescape_string(r"a\r\nb"), "a\r\nb");
+        // A non-ASCII character after a backslash is written as an escape, so the backslash is too
+        assert_eq!(escape_string_ascii("\\\u{e9}"), r"\\\u00e9");
+    }
+
+    #[test]
+    fn without_passes_a_character_through() {
+        let cfg = EscapeConfig::default().without('\t');
+        assert_eq!(escape_string_with("a\tb\n", &cfg), "a\tb\\n");
+        assert_eq!(unescape_string_with(r"a\tb\n", &cfg), "a\\tb\n");
+        assert_eq!(unescape_string_with(&escape_string_with("a\tb", &cfg), &cfg), "a\tb");
+        assert_eq!(escape_string("a\tb"), r"a\tb");
+        assert_eq!(cfg.sequences.len(), ESCAPE_SEQUENCES.len() - 1);
+    }
+}
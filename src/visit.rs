@@ -0,0 +1,93 @@
+use crate::{Atom, Item, KeyAtom, Operator, Value};
+
+// Read-only AST traversal. Every method does nothing by default; override the ones you need.
+// `walk` calls them in source order, parents before their contents.
+pub trait Visitor {
+    // A key/value pair; its value is visited next
+    fn visit_pair(&mut self, _key: &KeyAtom, _op: &Operator, _value: &Value) {}
+    // Any value, whether a pair's or standalone
+    fn visit_value(&mut self, _value: &Value) {}
+    // The items of a block, before they are walked
+    fn visit_block(&mut self, _items: &[Item]) {}
+    // A single atom, standalone or inside an array
+    fn visit_atom(&mut self, _atom: &Atom) {}
+    // A comment line or a trailing comment, with its leading `#`
+    fn visit_comment(&mut self, _text: &str) {}
+}
+
+// Visit every item, recursing into nested blocks and arrays
+pub fn walk(items: &[Item], v: &mut impl Visitor) {
+    for it in items {
+        match it {
+            Item::Pair { key, op, value, trailing_comment, leading_comments } => {
+                for c in leading_comments { v.visit_comment(c); }
+                v.visit_pair(key, op, value);
+                walk_value(value, v);
+                if let Some(c) = trailing_comment { v.visit_comment(c); }
+            }
+            Item::Value { value, trailing_comment } => {
+                walk_value(value, v);
+                if let Some(c) = trailing_comment { v.visit_comment(c); }
+            }
+            Item::Comment(c) => v.visit_comment(c),
+            Item::Blank(_) => {}
+        }
+    }
+}
+
+fn walk_value(value: &Value, v: &mut impl Visitor) {
+    v.visit_value(value);
+    match value {
+        Value::Atom(a) => v.visit_atom(a),
+        Value::Array(arr) => arr.iter().for_each(|a| v.visit_atom(a)),
+        Value::Block(items) => {
+            v.visit_block(items);
+            walk(items, v);
+        }
+        Value::Color { .. } => {}
+    }
+}
+
+// In-place AST rewriting, mirroring Visitor. Each node is visited before its contents,
+// so `walk_mut` descends into whatever a method leaves behind.
+pub trait VisitorMut {
+    fn visit_pair_mut(&mut self, _key: &mut KeyAtom, _op: &mut Operator, _value: &mut Value) {}
+    fn visit_value_mut(&mut self, _value: &mut Value) {}
+    // The items of a block; items may be added or removed here
+    fn visit_block_mut(&mut self, _items: &mut Vec<Item>) {}
+    fn visit_atom_mut(&mut self, _atom: &mut Atom) {}
+    fn visit_comment_mut(&mut self, _text: &mut String) {}
+}
+
+// Visit every item mutably, recursing into nested blocks and arrays
+pub fn walk_mut(items: &mut [Item], v: &mut impl VisitorMut) {
+    for it in items.iter_mut() {
+        match it {
+            Item::Pair { key, op, value, trailing_comment, leading_comments } => {
+                for c in leading_comments.iter_mut() { v.visit_comment_mut(c); }
+                v.visit_pair_mut(key, op, value);
+                walk_value_mut(value, v);
+                if let Some(c) = trailing_comment { v.visit_comment_mut(c); }
+            }
+            Item::Value { value, trailing_comment } => {
+                walk_value_mut(value, v);
+                if let Some(c) = trailing_comment { v.visit_comment_mut(c); }
+            }
+            Item::Comment(c) => v.visit_comment_mut(c),
+            Item::Blank(_) => {}
+        }
+    }
+}
+
+fn walk_value_mut(value: &mut Value, v: &mut impl VisitorMut) {
+    v.visit_value_mut(value);
+    match value {
+        Value::Atom(a) => v.visit_atom_mut(a),
+        Value::Array(arr) => arr.iter_mut().for_each(|a| v.visit_atom_mut(a)),
+        Value::Block(items) => {
+            v.visit_block_mut(items);
+            walk_mut(items, v);
+        }
+        Value::Color { .. } => {}
+    }
+}
@@ -0,0 +1,37 @@
+// Allocation counts for the serializer, measured with a counting global allocator
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clausewitz_script_parser::{parse_str, serialize_ast};
+
+// Reallocations that grew a buffer to at least LARGE bytes; only the output text of a big file gets that big
+const LARGE: usize = 1 << 20;
+static LARGE_REALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 { System.alloc(layout) }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) { System.dealloc(ptr, layout) }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= LARGE { LARGE_REALLOCS.fetch_add(1, Ordering::Relaxed); }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn serialize_ast_sizes_its_output_once() {
+    let input: String = (0..20_000)
+        .map(|i| format!("idea_{} = {{\n\tcost = {}\n\tmodifier = {{ stability_factor = 0.05 political_power_gain = 0.1 }}\n\tprovinces = {{ 1 2 3 4 5 }}\n}}\n", i, i))
+        .collect();
+    let items = parse_str(&input).unwrap();
+    let before = LARGE_REALLOCS.load(Ordering::Relaxed);
+    let out = serialize_ast(&items);
+    let grown = LARGE_REALLOCS.load(Ordering::Relaxed) - before;
+    assert!(out.len() > 2 * LARGE);
+    // Growing a String from empty to this size would reallocate it at 1, 2 and 4 MiB
+    assert_eq!(grown, 0, "output buffer regrew {} times", grown);
+}
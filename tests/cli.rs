@@ -0,0 +1,58 @@
+// The binary's `--format` mode, driven through a pipe as an editor would
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs, process};
+
+// Run `--format` in an empty directory with `input` on stdin; also returns whether the directory stayed empty
+fn format(input: &str) -> (Output, bool) {
+    let dir = env::temp_dir().join(format!("clausewitz-cli-{}-{}", process::id(), input.len()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_clausewitz-script-parser"))
+        .arg("--format")
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let untouched = fs::read_dir(&dir).unwrap().next().is_none();
+    fs::remove_dir_all(&dir).unwrap();
+    (output, untouched)
+}
+
+#[test]
+fn format_pipes_stdin_to_stdout() {
+    let (output, untouched) = format("a={b=1 # note\n}\n\nc = yes");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a = {\n  b = 1 # note\n}\n\nc = yes\n");
+    assert!(output.stderr.is_empty());
+    assert!(untouched);
+}
+
+#[test]
+fn format_reports_every_error_and_fails() {
+    let (output, untouched) = format("a = = 1\nb = 2\nc = }\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 2, "{}", stderr);
+    assert!(stderr.lines().all(|l| l.starts_with("error: ")));
+    assert!(untouched);
+}
+
+#[test]
+fn format_takes_no_paths() {
+    let output = Command::new(env!("CARGO_BIN_EXE_clausewitz-script-parser")).args(["--format", "in.txt"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn format_rejects_a_missing_operator_like_the_parser() {
+    let (output, _) = format("x { a = 1 }\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: missing operator between key 'x'"), "{}", stderr);
+}
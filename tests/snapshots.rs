@@ -0,0 +1,33 @@
+// Golden AST snapshots: every fixture in tests/fixtures is parsed and its Debug dump compared with
+// tests/snapshots/<name>.ast, so a grammar or parser change that alters existing results fails here.
+// After an intended change, rewrite the snapshots with `UPDATE_SNAPSHOTS=1 cargo test --test snapshots`
+// and review the diff.
+use std::path::Path;
+use std::{env, fs};
+
+use clausewitz_script_parser::parse_str;
+
+#[test]
+fn fixtures_match_snapshots() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut fixtures: Vec<_> = fs::read_dir(root.join("fixtures")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures found");
+    let mut changed = Vec::new();
+    for path in &fixtures {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let items = parse_str(&fs::read_to_string(path).unwrap()).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let dump = format!("{:#?}\n", items);
+        let snapshot = root.join("snapshots").join(format!("{}.ast", name));
+        if update {
+            fs::write(&snapshot, &dump).unwrap();
+        } else if fs::read_to_string(&snapshot).ok().as_deref() != Some(dump.as_str()) {
+            changed.push(name.into_owned());
+        }
+    }
+    assert!(changed.is_empty(), "parse result changed for {:?}; if intended, rerun with UPDATE_SNAPSHOTS=1 and review the diff", changed);
+}
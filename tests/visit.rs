@@ -0,0 +1,54 @@
+// Visitors run over the fixtures, as analysis tools would use them
+use clausewitz_script_parser::{parse_str, serialize_ast, walk, walk_mut, Atom, Item, KeyAtom, Number, Operator, Value, Visitor, VisitorMut};
+
+const IDEAS: &str = include_str!("fixtures/ideas.txt");
+
+// Counts pairs, blocks and comments, and collects the keys in visiting order
+#[derive(Default)]
+struct Counter { pairs: usize, blocks: usize, atoms: usize, comments: Vec<String>, keys: Vec<String> }
+
+impl Visitor for Counter {
+    fn visit_pair(&mut self, key: &KeyAtom, _op: &Operator, _value: &Value) {
+        self.pairs += 1;
+        if let KeyAtom::Ident(k) = key { self.keys.push(k.clone()); }
+    }
+    fn visit_block(&mut self, _items: &[Item]) { self.blocks += 1; }
+    fn visit_atom(&mut self, _atom: &Atom) { self.atoms += 1; }
+    fn visit_comment(&mut self, text: &str) { self.comments.push(text.to_string()); }
+}
+
+#[test]
+fn counts_every_pair_in_the_ideas_fixture() {
+    let mut counter = Counter::default();
+    walk(&parse_str(IDEAS).unwrap(), &mut counter);
+    assert_eq!(counter.pairs, 11);
+    assert_eq!(counter.blocks, 5);
+    assert_eq!(counter.atoms, 6);
+    assert_eq!(counter.comments, ["# Starting idea", "# +10%"]);
+    // Parents come before their contents, in source order
+    assert_eq!(counter.keys[..4], ["ideas", "country", "GER_rearmament", "picture"]);
+    assert_eq!(counter.keys.last().unwrap(), "cost");
+}
+
+// Doubles every number, in atoms, arrays and nested blocks alike
+struct Doubler;
+
+impl VisitorMut for Doubler {
+    fn visit_atom_mut(&mut self, atom: &mut Atom) {
+        if let Atom::Number(n) = atom { *n = Number::from(n.value * 2.0); }
+    }
+}
+
+#[test]
+fn doubles_every_number_and_reserializes() {
+    let mut items = parse_str(IDEAS).unwrap();
+    walk_mut(&mut items, &mut Doubler);
+    let out = serialize_ast(&items);
+    assert!(out.contains("industrial_capacity_factory = 0.2 # +10%\n"), "{}", out);
+    assert!(out.contains("production_speed_buildings_factor = -0.1\n"), "{}", out);
+    assert!(out.contains("stability_factor = 0.03\n"), "{}", out);
+    assert!(out.contains("cost = 300\n"), "{}", out);
+    let mut list = parse_str("provinces = { 1 2 3 }\nyes_no = yes").unwrap();
+    walk_mut(&mut list, &mut Doubler);
+    assert_eq!(serialize_ast(&list), "provinces = {\n  2 4 6\n}\nyes_no = yes\n");
+}